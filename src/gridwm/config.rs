@@ -15,6 +15,9 @@ pub struct Config {
     pub desktop: Desktop,
     pub bar: Bar,
     pub keybinds: Keybinds,
+    pub mousebinds: Mousebinds,
+    pub scratchpads: Vec<Scratchpad>,
+    pub rules: Vec<Rule>,
 }
 
 // window section of config
@@ -26,6 +29,15 @@ pub struct Window {
     pub window_bar_height: u32,
     pub text_color: String,
     pub background_color: String,
+    pub snap_distance: u32,
+    pub snap_zones: bool,
+    pub placement: String,
+    pub close_timeout: f32,
+    pub border_width: u32,
+    pub border_focused: String,
+    pub border_unfocused: String,
+    pub border_urgent: String,
+    pub border_floating: String,
 }
 
 impl Default for Window {
@@ -36,6 +48,15 @@ impl Default for Window {
             window_bar_height: 20,
             text_color: "#ffffff".into(),
             background_color: "#272727".into(),
+            snap_distance: 10,
+            snap_zones: true,
+            placement: "center".into(),
+            close_timeout: 5.0,
+            border_width: 1,
+            border_focused: "#5294e2".into(),
+            border_unfocused: "#272727".into(),
+            border_urgent: "#e25252".into(),
+            border_floating: "#5c5c5c".into(),
         }
     }
 }
@@ -45,12 +66,14 @@ impl Default for Window {
 #[serde(default)]
 pub struct General {
     pub update_ms: String,
+    pub menu: String,
 }
 
 impl Default for General {
     fn default() -> Self {
         Self {
-            update_ms: "5".to_string(),
+            update_ms: "0".to_string(),
+            menu: "dmenu -i -l 10".to_string(),
         }
     }
 }
@@ -150,11 +173,68 @@ impl Default for Keybinds {
     }
 }
 
+// mousebinds section of config
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct Mousebinds {
+    pub window: Option<Vec<Vec<String>>>,
+    pub title_bar: Vec<Vec<String>>,
+    pub root: Vec<Vec<String>>,
+    pub bar: Vec<Vec<String>>,
+}
+
 // start section of config
 #[derive(Debug, Deserialize, Default)]
 #[serde(default)]
 pub struct Start {
     pub exec: Vec<String>,
+    pub restore_session: bool,
+}
+
+// scratchpads section of config
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Scratchpad {
+    pub name: String,
+    pub class: String,
+    pub command: String,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Scratchpad {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            class: String::new(),
+            command: String::new(),
+            width: 0.5,
+            height: 0.5,
+        }
+    }
+}
+
+// rules section of config
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Rule {
+    // what to match
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub role: Option<String>,
+    pub window_type: Option<String>,
+    pub title: Option<String>,
+    // what to do with the window
+    pub desktop: Option<usize>,
+    pub floating: Option<bool>,
+    pub geometry: Option<[i32; 4]>,
+    pub fullscreen: Option<bool>,
+    pub no_title_bar: Option<bool>,
+    pub focus: Option<bool>,
+    pub sticky: Option<bool>,
+    pub terminal: Option<bool>,
+    pub no_swallow: Option<bool>,
+    pub placement: Option<String>,
 }
 
 // ratio for mouse acceleration
@@ -169,6 +249,22 @@ impl RatioF64 {
 
 // functions for config
 impl Config {
+    // mousebinds on windows, built from move_mod and resize_mod if not configured
+    pub fn window_mousebinds(&self) -> Vec<Vec<String>> {
+        if let Some(binds) = &self.mousebinds.window {
+            return binds.clone();
+        }
+
+        let move_mod = &self.keybinds.move_mod;
+        let resize_mod = &self.keybinds.resize_mod;
+        vec![
+            vec![format!("{}+BUTTON1", move_mod), "move".into()],
+            vec![format!("{}+BUTTON3", resize_mod), "resize".into()],
+            vec![format!("{}+BUTTON4", resize_mod), "scale_up".into()],
+            vec![format!("{}+BUTTON5", resize_mod), "scale_down".into()],
+        ]
+    }
+
     pub fn from_file(path: &str) -> Result<Self, GridWMError> {
         match fs::read_to_string(path) {
             Ok(s) => {
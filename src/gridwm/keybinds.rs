@@ -1,45 +1,249 @@
-use x11::xlib;
+use log::*;
+use std::{collections::HashMap, ffi::CString};
+use x11::xlib::{self, KeySym};
 
-pub fn parse_keybind(display: *mut xlib::Display, keys: String) -> Option<(u32, Option<i32>)> {
-    let keys = keys.split("+").map(|s| s.trim()).collect::<Vec<&str>>();
+// what a key binding does
+#[derive(Debug, Clone)]
+pub enum KeyAction {
+    Gridwm(String),
+    Exec(String),
+}
+
+// a key by name, or a raw keycode that doesn't depend on the layout
+#[derive(Debug, Clone, Copy)]
+pub enum Key {
+    Sym(KeySym),
+    Code(u32),
+}
+
+// a key binding as written in the config, the keycode is looked up when grabbing
+#[derive(Debug, Clone)]
+pub struct Keybind {
+    pub mask: u32,
+    pub key: Key,
+    pub action: KeyAction,
+}
+
+// modifiers that are compared when matching binds, lock keys are ignored
+pub const RELEVANT_MODIFIERS: u32 = xlib::ControlMask
+    | xlib::ShiftMask
+    | xlib::Mod1Mask
+    | xlib::Mod3Mask
+    | xlib::Mod4Mask
+    | xlib::Mod5Mask;
 
+// caps lock and num lock combinations grabbed along with every bind
+pub const EXTRA_MODS: [u32; 4] = [
+    0,
+    xlib::LockMask,
+    xlib::Mod2Mask,
+    xlib::LockMask | xlib::Mod2Mask,
+];
+
+pub fn parse_keybind(keys: &str) -> Option<(u32, Option<Key>)> {
     let mut mask = 0;
-    let mut key: Option<String> = None;
+    let mut key = None;
 
-    for k in keys {
-        let k_upper = k.to_uppercase();
-        match k_upper.as_str() {
-            "CTRL" | "CONTROL" => mask |= xlib::ControlMask,
-            "SHIFT" => mask |= xlib::ShiftMask,
-            "ALT" => mask |= xlib::Mod1Mask,
-            "SUPER" | "WIN" | "WINDOWS" | "MOD4" => mask |= xlib::Mod4Mask,
-            _ => key = Some(k_upper.clone()),
+    for k in keys.split("+").map(|s| s.trim()) {
+        match parse_modifier(k) {
+            Some(modifier) => mask |= modifier,
+            None => key = Some(parse_key(k)?),
         }
     }
 
-    if let Some(k) = key {
-        let keysym = if k.len() == 1 {
-            Some(k.chars().next().unwrap() as u32)
-        } else {
-            match k.as_str() {
-                "SPACE" => Some(x11::keysym::XK_space),
-                "TAB" => Some(x11::keysym::XK_Tab),
-                "ENTER" => Some(x11::keysym::XK_Return),
-                "RIGHT" => Some(x11::keysym::XK_Right),
-                "LEFT" => Some(x11::keysym::XK_Left),
-                "UP" => Some(x11::keysym::XK_Up),
-                "DOWN" => Some(x11::keysym::XK_Down),
-                _ => None,
-            }
+    Some((mask, key))
+}
+
+// names that differ from the keysym names or are commonly written differently
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("enter", "Return"),
+    ("esc", "Escape"),
+    ("backspace", "BackSpace"),
+    ("del", "Delete"),
+    ("ins", "Insert"),
+    ("pageup", "Prior"),
+    ("pagedown", "Next"),
+    ("printscreen", "Print"),
+    ("capslock", "Caps_Lock"),
+    ("numlock", "Num_Lock"),
+    ("scrolllock", "Scroll_Lock"),
+];
+
+// resolves a key name like "q", "F1", "Escape" or "XF86AudioRaiseVolume", or "keycode:NN"
+fn parse_key(name: &str) -> Option<Key> {
+    if let Some((prefix, code)) = name.split_once(':')
+        && prefix.eq_ignore_ascii_case("keycode")
+    {
+        return code
+            .trim()
+            .parse()
+            .ok()
+            .filter(|c| (8..=255).contains(c))
+            .map(Key::Code);
+    }
+
+    // single characters are latin-1 keysyms, so punctuation works without its name
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && (c as u32) < 0x100
+    {
+        return Some(Key::Sym(c.to_ascii_lowercase() as KeySym));
+    }
+
+    let lower = name.to_lowercase();
+    let alias = KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, keysym)| keysym.to_string());
+    let mut chars = lower.chars();
+    let capitalized = chars
+        .next()
+        .map(|c| c.to_uppercase().collect::<String>() + chars.as_str());
+
+    // keysym names are case sensitive, try the usual spellings of what was written
+    [
+        Some(name.to_string()),
+        alias,
+        capitalized,
+        Some(lower),
+        Some(name.to_uppercase()),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|candidate| {
+        let candidate = CString::new(candidate).ok()?;
+        let keysym = unsafe { xlib::XStringToKeysym(candidate.as_ptr()) };
+        (keysym != 0).then_some(Key::Sym(keysym))
+    })
+}
+
+// parses the gridwm and exec binds once, invalid ones are reported and skipped
+pub fn parse_keybinds(gridwm: &[Vec<String>], exec: &[Vec<String>]) -> Vec<Keybind> {
+    let binds = gridwm
+        .iter()
+        .map(|b| (b, false))
+        .chain(exec.iter().map(|b| (b, true)));
+
+    let mut keybinds = Vec::new();
+    for (bind, exec) in binds {
+        if bind.len() != 2 {
+            error!("failed to parse keybind {:?}: invalid length.", bind);
+            continue;
+        }
+        match parse_keybind(&bind[0]) {
+            Some((mask, Some(key))) => keybinds.push(Keybind {
+                mask,
+                key,
+                action: if exec {
+                    KeyAction::Exec(bind[1].clone())
+                } else {
+                    KeyAction::Gridwm(bind[1].clone())
+                },
+            }),
+            Some((_, None)) => warn!("keybind {:?} has no key", bind),
+            None => warn!("failed to parse keybind: {:?}", bind),
+        }
+    }
+    keybinds
+}
+
+// maps (modifiers, keycode) to actions with the current keyboard mapping
+pub fn key_table(
+    display: *mut xlib::Display,
+    keybinds: &[Keybind],
+) -> HashMap<(u32, u32), KeyAction> {
+    let mut table = HashMap::new();
+    for bind in keybinds {
+        let keycode = match bind.key {
+            Key::Code(keycode) => keycode,
+            Key::Sym(keysym) => unsafe { xlib::XKeysymToKeycode(display, keysym) as u32 },
         };
-        let mut keycode = None;
-        if let Some(key) = keysym {
-            keycode = unsafe { Some(xlib::XKeysymToKeycode(display, key as u64) as i32) };
+        if keycode == 0 {
+            debug!("no key for {:?} in the current layout", bind.key);
+            continue;
+        }
+        if table.contains_key(&(bind.mask, keycode)) {
+            warn!("key bound twice, ignoring {:?}", bind.action);
+            continue;
+        }
+        table.insert((bind.mask, keycode), bind.action.clone());
+    }
+    table
+}
+
+// what a mouse binding applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseTarget {
+    Window,
+    TitleBar,
+    Root,
+    Bar,
+}
+
+// (target, modifiers, button, on release) -> action
+pub type MouseTable = HashMap<(MouseTarget, u32, u32, bool), String>;
+
+// parses the mousebinds once, the first bind for a combination wins
+pub fn parse_mousebinds(
+    window: &[Vec<String>],
+    title_bar: &[Vec<String>],
+    root: &[Vec<String>],
+    bar: &[Vec<String>],
+) -> MouseTable {
+    let parse = |binds: &[Vec<String>]| -> Vec<((u32, u32, bool), String)> {
+        let mut parsed = Vec::new();
+        for bind in binds {
+            if bind.len() != 2 {
+                error!("failed to parse mousebind {:?}: invalid length.", bind);
+                continue;
+            }
+            match parse_mousebind(&bind[0]) {
+                Some(combination) => parsed.push((combination, bind[1].clone())),
+                None => warn!("failed to parse mousebind: {:?}", bind),
+            }
+        }
+        parsed
+    };
+    let window = parse(window);
+
+    // title bars fall back to the window mousebinds
+    let binds = [
+        (MouseTarget::Window, window.clone()),
+        (MouseTarget::TitleBar, parse(title_bar)),
+        (MouseTarget::TitleBar, window),
+        (MouseTarget::Root, parse(root)),
+        (MouseTarget::Bar, parse(bar)),
+    ];
+
+    let mut table = HashMap::new();
+    for (target, binds) in binds {
+        for ((mask, button, release), action) in binds {
+            table
+                .entry((target, mask, button, release))
+                .or_insert(action);
+        }
+    }
+    table
+}
+
+// returns (mask, button, on release)
+pub fn parse_mousebind(bind: &str) -> Option<(u32, u32, bool)> {
+    let mut mask = 0;
+    let mut button = None;
+    let mut release = false;
+
+    for k in bind.split("+").map(|s| s.trim()) {
+        let k_upper = k.to_uppercase();
+        if k_upper == "RELEASE" {
+            release = true;
+        } else if let Some(num) = k_upper.strip_prefix("BUTTON") {
+            button = Some(num.parse::<u32>().ok().filter(|n| (1..=9).contains(n))?);
+        } else {
+            mask |= parse_modifier(k)?;
         }
-        Some((mask, keycode))
-    } else {
-        Some((mask, None))
     }
+
+    Some((mask, button?, release))
 }
 
 pub fn parse_modifier(modifier: &str) -> Option<u32> {
@@ -47,8 +251,10 @@ pub fn parse_modifier(modifier: &str) -> Option<u32> {
     match modifier_upper.as_str() {
         "CTRL" | "CONTROL" => Some(xlib::ControlMask),
         "SHIFT" => Some(xlib::ShiftMask),
-        "ALT" => Some(xlib::Mod1Mask),
+        "ALT" | "MOD1" => Some(xlib::Mod1Mask),
+        "HYPER" | "MOD3" => Some(xlib::Mod3Mask),
         "SUPER" | "WIN" | "WINDOWS" | "MOD4" => Some(xlib::Mod4Mask),
+        "ALTGR" | "MOD5" => Some(xlib::Mod5Mask),
         _ => None,
     }
 }
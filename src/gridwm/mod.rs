@@ -2,20 +2,30 @@ mod bar;
 mod config;
 mod error;
 mod keybinds;
+mod process;
+mod rules;
+mod session;
 mod signals;
+mod state;
+mod wakeup;
 
 use bar::*;
 use config::Config;
 use error::*;
 use keybinds::*;
+use rules::*;
 use signals::*;
+use wakeup::Wakeup;
 
 use log::*;
 use std::{
     collections::{BTreeSet, HashMap, HashSet},
     ffi::CString,
+    io::Write,
     mem::zeroed,
-    process::Command,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
     slice,
     sync::mpsc,
     thread,
@@ -26,12 +36,33 @@ use x11::{
     xlib::{
         self, Atom, Cursor, GCForeground, XAllocColor, XButtonPressedEvent, XClearWindow, XColor,
         XCreateFontCursor, XDefaultColormap, XDefaultRootWindow, XDefaultScreen, XFlush, XGCValues,
-        XGetWindowProperty, XInternAtom, XParseColor, XSetWindowBackground, XUnmapWindow,
-        XWindowAttributes,
+        XGetWindowProperty, XInternAtom, XParseColor, XSetWindowBackground, XWindowAttributes,
+    },
+    xrandr::{
+        RRMode, RRScreenChangeNotify, RRScreenChangeNotifyMask, XRRFreeCrtcInfo,
+        XRRFreeScreenResources, XRRGetCrtcInfo, XRRGetScreenResources, XRRQueryExtension,
+        XRRSelectInput, XRRUpdateConfiguration,
     },
-    xrandr::{RRMode, XRRGetCrtcInfo, XRRGetScreenResources},
 };
 
+// WM_STATE values from ICCCM
+const WITHDRAWN_STATE: i64 = 0;
+const NORMAL_STATE: i64 = 1;
+const ICONIC_STATE: i64 = 3;
+
+// how long to wait for the window of a program started by gridwm
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+// shapes from the X cursor font
+const CURSOR_BOTTOM_LEFT: u32 = 12;
+const CURSOR_BOTTOM_RIGHT: u32 = 14;
+const CURSOR_BOTTOM: u32 = 16;
+const CURSOR_LEFT: u32 = 70;
+const CURSOR_RIGHT: u32 = 96;
+const CURSOR_TOP_LEFT: u32 = 134;
+const CURSOR_TOP_RIGHT: u32 = 136;
+const CURSOR_TOP: u32 = 138;
+
 unsafe extern "C" fn x_error_handler(
     _display: *mut xlib::Display,
     _event: *mut xlib::XErrorEvent,
@@ -41,25 +72,60 @@ unsafe extern "C" fn x_error_handler(
 
 pub struct GridWM {
     display: *mut xlib::Display,
+    display_name: String,
     config: Config,
+    keybinds: Vec<Keybind>,
+    rules: Vec<CompiledRule>,
+    key_table: HashMap<(u32, u32), KeyAction>,
+    mouse_table: MouseTable,
     desktops: Vec<BTreeSet<Window>>,
-    current_desktop: usize,
+    monitors: Vec<Monitor>,
+    current_monitor: usize,
+    focused: Option<Window>,
     drag_state: Option<DragState>,
+    resize_state: Option<ResizeState>,
     floating_windows: BTreeSet<Window>,
+    float_geometry: HashMap<Window, WindowInfo>,
     bar_gc: xlib::GC,
     bar_background_gc: xlib::GC,
     win_bar_gc: xlib::GC,
     win_bar_background_gc: xlib::GC,
     bar_str: String,
-    screen_width: i16,
-    screen_height: i16,
     win_bar_windows: HashMap<Window, Window>,
     refresh_rate: i16,
+    // general.update_ms, None for "auto"
+    update_ms: Option<u64>,
+    randr_event_base: i32,
     trigger_redraw: bool,
+    wakeup: Wakeup,
+    scratchpad: BTreeSet<Window>,
+    named_scratchpads: HashMap<String, Window>,
+    // name and when its command was started
+    pending_scratchpads: HashMap<String, Instant>,
+    no_title_bar: BTreeSet<Window>,
+    skip_focus: HashSet<Window>,
+    minimized: Vec<Window>,
+    ignore_unmaps: HashMap<Window, usize>,
+    // shown again by us, e.g. when switching desktops, so they don't take focus
+    ignore_maps: HashMap<Window, usize>,
+    picker: Option<(PickerAction, mpsc::Receiver<Option<usize>>)>,
+    // (close requested at, answered a ping)
+    pending_closes: HashMap<Window, (Instant, bool)>,
+    sticky: BTreeSet<Window>,
+    terminals: BTreeSet<Window>,
+    swallowed: HashMap<Window, Window>,
+    restart_state: Option<state::RestartState>,
+    pending_session: Vec<PendingLaunch>,
+    // most recently focused first
+    focus_history: Vec<Window>,
+    switcher: Option<Switcher>,
+    tab_groups: Vec<TabGroup>,
+    border_colors: BorderColors,
 }
 
 pub type Window = u64;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct WindowInfo {
     x: i32,
     y: i32,
@@ -67,6 +133,55 @@ struct WindowInfo {
     h: i32,
 }
 
+// what to do with the entry picked from the menu
+enum PickerAction {
+    Restore(Vec<Window>),
+    Kill(Window),
+}
+
+// program relaunched by session_restore whose window hasn't shown up yet
+struct PendingLaunch {
+    saved: session::SessionWindow,
+    pid: u32,
+    started: Instant,
+}
+
+// alt-tab style window switcher
+struct Switcher {
+    windows: Vec<Window>,
+    index: usize,
+    overlay: Window,
+}
+
+// pixel values for window borders
+struct BorderColors {
+    focused: u64,
+    unfocused: u64,
+    urgent: u64,
+    floating: u64,
+}
+
+// an output showing one of the desktops
+struct Monitor {
+    area: WindowInfo,
+    desktop: usize,
+}
+
+// clients sharing one tile, only the active one is mapped
+struct TabGroup {
+    windows: Vec<Window>,
+    active: usize,
+}
+
+// what a mouse button was pressed on
+#[derive(Debug, Clone, Copy)]
+enum ClickTarget {
+    Window(Window),
+    TitleBar(Window),
+    Root,
+    Bar,
+}
+
 #[derive(Debug, Clone, Copy)]
 struct DragState {
     window: Window,
@@ -76,9 +191,27 @@ struct DragState {
     start_mouse_y: i32,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct ResizeState {
+    window: Window,
+    start_win_x: i32,
+    start_win_y: i32,
+    start_win_w: i32,
+    start_win_h: i32,
+    start_mouse_x: i32,
+    start_mouse_y: i32,
+    // -1 moves the left/top edge, 1 the right/bottom one, 0 neither
+    dir_x: i32,
+    dir_y: i32,
+    min_w: i32,
+    min_h: i32,
+    cursor: Cursor,
+}
+
 impl GridWM {
     pub fn new(display_name: &str) -> Result<Self, GridWMError> {
-        let current_desktop = 0;
+        // state left behind by the restart action
+        let restart_state = state::take(display_name);
 
         let display: *mut xlib::Display =
             unsafe { xlib::XOpenDisplay(CString::new(display_name)?.as_ptr()) };
@@ -118,8 +251,7 @@ impl GridWM {
         };
 
         // get bar content
-        let mut bar_str = get_widgets(&config.bar.widgets);
-        bar_str = bar_str.replace("DESKTOP_HERE", &desktop_widget(current_desktop));
+        let bar_str = get_widgets(&config.bar.widgets);
 
         // colors for window top bar
         let (win_bar_background_gc, win_bar_gc) = match create_gc(
@@ -137,74 +269,103 @@ impl GridWM {
             }
         };
 
-        let (screen_width, screen_height) = match get_screen_size(display) {
-            Ok(wh) => wh,
-            Err(_) => {
-                error!("failed to get screen size");
-                return Err(GridWMError::ScreenNotFound("0".to_string()));
-            }
+        let border_colors = BorderColors {
+            focused: alloc_color(display, &config.window.border_focused, "focused border"),
+            unfocused: alloc_color(display, &config.window.border_unfocused, "unfocused border"),
+            urgent: alloc_color(display, &config.window.border_urgent, "urgent border"),
+            floating: alloc_color(display, &config.window.border_floating, "floating border"),
         };
 
-        let refresh_rate: i16 = unsafe {
-            let root = XDefaultRootWindow(display);
-
-            let screen_resources = XRRGetScreenResources(display, root);
-            let mut active_mode_id: RRMode = 0;
-            for i in 0..(*screen_resources).ncrtc {
-                let crtc = *(*screen_resources).crtcs.offset(i as isize);
-                let crtc_info = XRRGetCrtcInfo(display, screen_resources, crtc);
-
-                if (*crtc_info).mode != 0 {
-                    active_mode_id = (*crtc_info).mode;
-                }
-            }
-
-            let mut active_rate = 0;
-            for i in 0..(*screen_resources).nmode {
-                let mode_info = (*screen_resources).modes.offset(i as isize);
-                if (*mode_info).id == active_mode_id {
-                    let total_pixels = (*mode_info).hTotal as u64 * (*mode_info).vTotal as u64;
-                    if total_pixels > 0 {
-                        active_rate = (*mode_info).dotClock / total_pixels;
-                    }
-                }
-            }
-
-            if active_rate > 0 {
-                active_rate as i16
-            } else {
-                60
+        let areas = match get_monitors(display) {
+            Ok(areas) => areas,
+            Err(e) => {
+                error!("failed to get monitors: {}", e);
+                return Err(e);
             }
         };
+        // monitors show the first desktops unless restarted with the same setup
+        let saved_desktops = restart_state
+            .as_ref()
+            .map(|s| s.monitor_desktops.clone())
+            .filter(|d| d.len() == areas.len())
+            .unwrap_or_else(|| (0..areas.len()).collect());
+        let monitors: Vec<Monitor> = areas
+            .into_iter()
+            .zip(saved_desktops)
+            .map(|(area, desktop)| Monitor { area, desktop })
+            .collect();
+        let current_monitor = restart_state
+            .as_ref()
+            .map_or(0, |s| s.current_monitor)
+            .min(monitors.len() - 1);
+
+        let refresh_rate = get_refresh_rate(display);
+        let update_ms = parse_update_ms(&config.general.update_ms);
+
+        // randr events are numbered from the extension's event base
+        let mut randr_event_base = 0;
+        let mut randr_error_base = 0;
+        if unsafe { XRRQueryExtension(display, &mut randr_event_base, &mut randr_error_base) } == 0
+        {
+            warn!("randr is not available, monitor changes won't be noticed");
+            randr_event_base = -1;
+        }
 
         Ok(GridWM {
             display,
+            display_name: display_name.to_string(),
+            restart_state,
+            pending_session: Vec::new(),
+            focus_history: Vec::new(),
+            switcher: None,
+            tab_groups: Vec::new(),
+            border_colors,
+            keybinds: parse_keybinds(&config.keybinds.gridwm, &config.keybinds.exec),
+            rules: compile_rules(&config.rules),
+            mouse_table: parse_mousebinds(
+                &config.window_mousebinds(),
+                &config.mousebinds.title_bar,
+                &config.mousebinds.root,
+                &config.mousebinds.bar,
+            ),
+            key_table: HashMap::new(),
             config,
             desktops,
-            current_desktop,
+            monitors,
+            current_monitor,
+            focused: None,
             drag_state: None,
+            resize_state: None,
             floating_windows: BTreeSet::new(),
+            float_geometry: HashMap::new(),
             bar_background_gc,
             bar_gc,
             win_bar_background_gc,
             win_bar_gc,
             bar_str,
-            screen_width,
-            screen_height,
             win_bar_windows: HashMap::new(),
             refresh_rate,
+            update_ms,
+            randr_event_base,
             trigger_redraw: true,
+            wakeup: Wakeup::new()?,
+            scratchpad: BTreeSet::new(),
+            named_scratchpads: HashMap::new(),
+            pending_scratchpads: HashMap::new(),
+            no_title_bar: BTreeSet::new(),
+            skip_focus: HashSet::new(),
+            minimized: Vec::new(),
+            ignore_unmaps: HashMap::new(),
+            ignore_maps: HashMap::new(),
+            picker: None,
+            pending_closes: HashMap::new(),
+            sticky: BTreeSet::new(),
+            terminals: BTreeSet::new(),
+            swallowed: HashMap::new(),
         })
     }
 
-    pub fn init(&self) -> Result<(), GridWMError> {
-        match simple_logger::init() {
-            Ok(_) => {}
-            Err(e) => {
-                println!("failed to start logger: {}", e);
-            }
-        }
-
+    pub fn init(&mut self) -> Result<(), GridWMError> {
         unsafe {
             xlib::XSetErrorHandler(Some(x_error_handler));
         }
@@ -247,98 +408,31 @@ impl GridWM {
             xlib::XSelectInput(
                 self.display,
                 root,
-                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                xlib::SubstructureRedirectMask
+                    | xlib::SubstructureNotifyMask
+                    | xlib::StructureNotifyMask
+                    | xlib::ButtonPressMask
+                    | xlib::ButtonReleaseMask,
             );
 
-            const EXTRA_MODS: [u32; 4] = [
-                0,
-                xlib::LockMask,
-                xlib::Mod2Mask,
-                xlib::LockMask | xlib::Mod2Mask,
-            ];
-
-            // grab keys for keybindings
-            for bind in self
-                .config
-                .keybinds
-                .gridwm
-                .iter()
-                .chain(self.config.keybinds.exec.iter())
-            {
-                if bind.len() != 2 {
-                    error!("failed to parse keybind {:?}: invalid length.", bind);
-                    continue;
-                }
-
-                let (mask, keycode): (u32, i32) = match parse_keybind(self.display, bind[0].clone())
-                {
-                    Some((a, Some(b))) => (a, b),
-                    Some((_, None)) => {
-                        continue;
-                    }
-                    _ => {
-                        warn!("failed to parse keybind: {:?}", bind);
-                        continue;
-                    }
-                };
-
-                for &extra_mod in &EXTRA_MODS {
-                    xlib::XGrabKey(
-                        self.display,
-                        keycode,
-                        mask | extra_mod,
-                        root,
-                        0,
-                        xlib::GrabModeAsync,
-                        xlib::GrabModeAsync,
-                    );
-                }
-            }
-
-            // for dragging
-            if let Some(modifier) = parse_modifier(&self.config.keybinds.move_mod) {
-                for &extra_mod in &EXTRA_MODS {
-                    xlib::XGrabButton(
-                        self.display,
-                        xlib::Button1,
-                        modifier | extra_mod,
-                        root,
-                        1,
-                        (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::Button1MotionMask)
-                            as u32,
-                        xlib::GrabModeAsync,
-                        xlib::GrabModeAsync,
-                        0,
-                        0,
-                    );
-                }
-            }
+            self.grab_keys();
 
-            // for resizing
-            if let Some(modifier) = parse_modifier(&self.config.keybinds.resize_mod) {
+            // mousebinds on windows are grabbed on the root window
+            let window_binds: HashSet<(u32, u32)> = self
+                .mouse_table
+                .keys()
+                .filter(|(target, ..)| *target == MouseTarget::Window)
+                .map(|&(_, mask, button, _)| (mask, button))
+                .collect();
+            for (mask, button) in window_binds {
                 for &extra_mod in &EXTRA_MODS {
-                    // scroll up
-                    xlib::XGrabButton(
-                        self.display,
-                        xlib::Button4,
-                        modifier | extra_mod,
-                        root,
-                        1,
-                        (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::Button1MotionMask)
-                            as u32,
-                        xlib::GrabModeAsync,
-                        xlib::GrabModeAsync,
-                        0,
-                        0,
-                    );
-                    // scroll down
                     xlib::XGrabButton(
                         self.display,
-                        xlib::Button5,
-                        modifier | extra_mod,
+                        button,
+                        mask | extra_mod,
                         root,
                         1,
-                        (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::Button1MotionMask)
+                        (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask)
                             as u32,
                         xlib::GrabModeAsync,
                         xlib::GrabModeAsync,
@@ -372,6 +466,11 @@ impl GridWM {
 
             xlib::XDefineCursor(self.display, root, cursor);
 
+            // monitors being plugged in or changed
+            if self.randr_event_base >= 0 {
+                XRRSelectInput(self.display, root, RRScreenChangeNotifyMask);
+            }
+
             // set background
             self.set_background(self.config.desktop.color.clone());
 
@@ -389,23 +488,26 @@ impl GridWM {
     pub fn run(&mut self) {
         info!("gridwm running");
 
-        for start_job in &self.config.start.exec {
-            match shell_words::split(start_job) {
-                Ok(parts) => {
-                    let program = &parts[0];
-                    let args = &parts[1..];
-                    let _ = Command::new(program).args(args).spawn();
-                }
-                Err(e) => {
-                    error!("failed to parse start job '{}': {}", start_job, e);
+        // start jobs are still running after a restart
+        let restarted = self.restart_state.is_some();
+        self.adopt_windows();
+
+        if !restarted {
+            for start_job in &self.config.start.exec {
+                if let Err(e) = spawn(start_job) {
+                    error!("failed to run start job '{}': {}", start_job, e);
                 }
             }
+            if self.config.start.restore_session {
+                self.session_restore(None);
+            }
         }
 
         let (timer_tx, timer_rx) = mpsc::channel();
 
         if self.config.bar.enable {
             let bar_config = self.config.bar.clone();
+            let waker = self.wakeup.waker();
 
             thread::spawn(move || {
                 loop {
@@ -415,6 +517,7 @@ impl GridWM {
                     if timer_tx.send(data).is_err() {
                         break;
                     }
+                    waker.wake();
                     let elapsed = proc_start.elapsed().as_millis();
                     if elapsed < (bar_config.update * 1000.0) as u128 {
                         thread::sleep(
@@ -433,10 +536,10 @@ impl GridWM {
         }
 
         let mut event: xlib::XEvent = unsafe { zeroed() };
-        let mut too_long_count = 0;
+        let x_fd = unsafe { xlib::XConnectionNumber(self.display) };
+        let mut last_redraw = Instant::now();
 
         loop {
-            let process_start = Instant::now();
             while unsafe { xlib::XPending(self.display) } > 0 {
                 unsafe {
                     xlib::XNextEvent(self.display, &mut event);
@@ -449,17 +552,18 @@ impl GridWM {
                             self.remove_window(event);
                             self.layout();
                         }
+                        xlib::DestroyNotify => {
+                            self.destroy_window(event);
+                            self.layout();
+                        }
                         xlib::MapNotify => {
-                            // set focus when window is mapped
+                            // set focus when a new window is mapped
                             let map_event: xlib::XMapEvent = From::from(event);
-                            let desktop = self.get_desktop(self.current_desktop);
-                            if desktop.contains(&map_event.window) {
-                                xlib::XSetInputFocus(
-                                    self.display,
-                                    map_event.window,
-                                    xlib::RevertToPointerRoot,
-                                    xlib::CurrentTime,
-                                );
+                            if !self.take_ignored_map(map_event.window)
+                                && self.is_visible(map_event.window)
+                                && !self.skip_focus.remove(&map_event.window)
+                            {
+                                self.focus(map_event.window);
                                 xlib::XRaiseWindow(self.display, map_event.window);
                             }
                             self.layout();
@@ -467,47 +571,29 @@ impl GridWM {
                         xlib::KeyPress => {
                             self.handle_key(event);
                         }
-                        xlib::ButtonPress => {
-                            let btn_event: xlib::XButtonPressedEvent = From::from(event);
-
-                            let is_drag_bind = if let Some(mask) =
-                                parse_modifier(&self.config.keybinds.move_mod)
+                        xlib::MappingNotify => {
+                            let mut mapping: xlib::XMappingEvent = From::from(event);
+                            xlib::XRefreshKeyboardMapping(&mut mapping);
+                            if mapping.request == xlib::MappingKeyboard
+                                || mapping.request == xlib::MappingModifier
                             {
-                                // TODO: don't hardcode
-                                let config_btn = xlib::Button1;
-                                (btn_event.state & mask == mask) && (btn_event.button == config_btn)
-                            } else {
-                                false
-                            };
-
-                            let is_scroll_up = if let Some(mask) =
-                                parse_modifier(&self.config.keybinds.resize_mod)
-                            {
-                                // TODO: don't hardcode
-                                let config_btn = xlib::Button4;
-                                (btn_event.state & mask == mask) && (btn_event.button == config_btn)
-                            } else {
-                                false
-                            };
-
-                            let is_scroll_down = if let Some(mask) =
-                                parse_modifier(&self.config.keybinds.resize_mod)
+                                self.grab_keys();
+                            }
+                        }
+                        xlib::KeyRelease => {
+                            self.handle_key_release(From::from(event));
+                        }
+                        xlib::Expose => {
+                            let expose: xlib::XExposeEvent = From::from(event);
+                            if expose.count == 0
+                                && self.switcher.as_ref().map(|s| s.overlay) == Some(expose.window)
                             {
-                                // TODO: don't hardcode
-                                let config_btn = xlib::Button5;
-                                (btn_event.state & mask == mask) && (btn_event.button == config_btn)
-                            } else {
-                                false
-                            };
-
-                            // TODO: make resize direction configurable
-                            if is_drag_bind {
-                                self.handle_drag_start(btn_event);
-                            } else if is_scroll_up {
-                                self.scale_up(btn_event);
-                            } else if is_scroll_down {
-                                self.scale_down(btn_event);
-                            } else {
+                                self.draw_switcher();
+                            }
+                        }
+                        xlib::ButtonPress => {
+                            let handled = self.handle_mousebind(From::from(event), false);
+                            if !handled {
                                 self.handle_button(event);
                             }
                         }
@@ -525,6 +611,30 @@ impl GridWM {
                         }
                         xlib::ButtonRelease => {
                             self.handle_release(From::from(event));
+                            self.handle_mousebind(From::from(event), true);
+                        }
+                        xlib::ClientMessage => {
+                            self.handle_client_message(From::from(event));
+                        }
+                        xlib::ConfigureNotify => {
+                            let configure: xlib::XConfigureEvent = From::from(event);
+                            if configure.window == XDefaultRootWindow(self.display) {
+                                self.update_monitors();
+                            }
+                        }
+                        t if self.randr_event_base >= 0
+                            && t == self.randr_event_base + RRScreenChangeNotify =>
+                        {
+                            XRRUpdateConfiguration(&mut event);
+                            self.update_monitors();
+                        }
+                        xlib::PropertyNotify => {
+                            let property: xlib::XPropertyEvent = From::from(event);
+                            if property.atom == xlib::XA_WM_HINTS {
+                                self.update_borders();
+                            } else if property.atom == xlib::XA_WM_NAME {
+                                self.trigger_redraw = true;
+                            }
                         }
                         _ => {
                             // debug!("event triggered: {:?}", event);
@@ -533,7 +643,18 @@ impl GridWM {
                 }
             }
 
-            if let Ok(data) = timer_rx.try_recv()
+            if let Some((_, picker)) = &self.picker
+                && let Ok(choice) = picker.try_recv()
+                && let Some((action, _)) = self.picker.take()
+                && let Some(choice) = choice
+            {
+                self.handle_pick(action, choice);
+            }
+
+            self.check_pending_closes();
+
+            // only the newest bar content matters
+            if let Some(data) = timer_rx.try_iter().last()
                 && self.config.bar.enable
                 && data != self.bar_str
             {
@@ -541,44 +662,30 @@ impl GridWM {
                 self.trigger_redraw = true;
             }
 
-            if self.config.bar.enable && self.trigger_redraw {
-                self.draw_bar(None);
-            }
-
-            if self.config.window.window_bars && self.trigger_redraw {
-                self.draw_window_bar();
-            }
-
-            // reset
-            self.trigger_redraw = false;
-
-            // flush
-            unsafe {
-                XFlush(self.display);
+            // draw at most once per throttle interval, the rest waits for the next one
+            let throttle = self.redraw_throttle();
+            let redraw_in = throttle.saturating_sub(last_redraw.elapsed());
+            if self.trigger_redraw && redraw_in.is_zero() {
+                if self.config.bar.enable {
+                    self.draw_bar(None);
+                }
+                if self.config.window.window_bars || !self.tab_groups.is_empty() {
+                    self.draw_window_bar();
+                }
+                self.trigger_redraw = false;
+                last_redraw = Instant::now();
             }
 
-            // get time from config or calculate from refresh rate
-            let sleep_time = match self.config.general.update_ms.as_str() {
-                "auto" => Duration::from_millis((1000.0 / self.refresh_rate as f64) as u64),
-                value => Duration::from_millis(
-                    value
-                        .parse()
-                        .expect("general.update_ms is not a valid number or set to \"auto\""),
-                ),
-            };
-
-            let process_took = process_start.elapsed();
-            if process_took < sleep_time {
-                too_long_count = 0;
-                thread::sleep(sleep_time - process_took);
-            } else {
-                too_long_count += 1;
+            // flush, events that arrived meanwhile are handled without sleeping
+            if unsafe { xlib::XPending(self.display) } > 0 {
+                continue;
             }
 
-            // it can sometimes take too long when resizing windows fast
-            if too_long_count > 5 {
-                warn!("main loop exceeded the expected duration 5 consecutive times");
+            let mut timeout = self.next_close_timeout();
+            if self.trigger_redraw {
+                timeout = Some(timeout.map_or(redraw_in, |t| t.min(redraw_in)));
             }
+            self.wakeup.wait(x_fd, timeout);
         }
     }
 
@@ -586,9 +693,33 @@ impl GridWM {
         if self.desktops.len() <= index {
             self.desktops.resize_with(index + 1, BTreeSet::new);
         }
+        // let other tools (and a restarted gridwm) know where windows live
+        for &window in &value {
+            let desktop = if self.sticky.contains(&window) {
+                0xFFFFFFFF
+            } else {
+                index as u64
+            };
+            self.set_cardinal(window, "_NET_WM_DESKTOP", desktop);
+        }
         self.desktops[index] = value;
     }
 
+    fn set_cardinal(&self, window: Window, name: &str, value: u64) {
+        unsafe {
+            xlib::XChangeProperty(
+                self.display,
+                window,
+                self.get_atom(name),
+                xlib::XA_CARDINAL,
+                32,
+                xlib::PropModeReplace,
+                &value as *const u64 as *const u8,
+                1,
+            );
+        }
+    }
+
     fn get_desktop(&self, index: usize) -> BTreeSet<Window> {
         match self.desktops.get(index) {
             Some(d) => d.clone(),
@@ -596,222 +727,2304 @@ impl GridWM {
         }
     }
 
+    // desktop shown on the focused monitor
+    fn current_desktop(&self) -> usize {
+        self.monitors[self.current_monitor].desktop
+    }
+
+    fn monitor_showing(&self, desktop: usize) -> Option<usize> {
+        self.monitors.iter().position(|m| m.desktop == desktop)
+    }
+
+    fn is_shown(&self, desktop: usize) -> bool {
+        self.monitor_showing(desktop).is_some()
+    }
+
+    // managed and on a desktop some monitor shows
+    fn is_visible(&self, window: Window) -> bool {
+        self.desktops
+            .iter()
+            .position(|d| d.contains(&window))
+            .is_some_and(|d| self.is_shown(d))
+    }
+
+    fn monitor_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.monitors.iter().position(|m| {
+            x >= m.area.x && x < m.area.x + m.area.w && y >= m.area.y && y < m.area.y + m.area.h
+        })
+    }
+
+    fn pointer_monitor(&self) -> usize {
+        let (_, x, y, _) = self.query_pointer();
+        self.monitor_at(x, y).unwrap_or(self.current_monitor)
+    }
+
+    // monitor showing the desktop of a window
+    fn monitor_of(&self, window: Window) -> usize {
+        self.monitor_showing(self.desktop_of(window))
+            .unwrap_or(self.current_monitor)
+    }
+
     fn create_window(&mut self, event: xlib::XEvent) {
         info!("creating a window");
         let event: xlib::XMapRequestEvent = From::from(event);
-        unsafe { xlib::XMapWindow(self.display, event.window) };
-        let mut desktop = self.get_desktop(self.current_desktop);
-        desktop.insert(event.window);
-        self.set_desktop(self.current_desktop, desktop);
+        self.manage(event.window, None, false);
     }
 
-    fn get_name(&self, window: Window) -> Result<String, GridWMError> {
-        let mut win_name: *mut i8 = std::ptr::null_mut();
-        let status = unsafe { xlib::XFetchName(self.display, window, &mut win_name) };
-        if status != 0 && !win_name.is_null() {
-            let win_title = unsafe {
-                std::ffi::CStr::from_ptr(win_name)
-                    .to_string_lossy()
-                    .into_owned()
-            };
-            unsafe { xlib::XFree(win_name as *mut _) };
-            return Ok(win_title);
+    // takes over a window, either on map request or at startup
+    fn manage(&mut self, window: Window, desktop: Option<usize>, adopted: bool) {
+        // minimized window wants to be shown again
+        if self.minimized.contains(&window) {
+            self.restore(window);
+            return;
         }
 
-        // if above fails
-        unsafe {
-            let net_wm_name = XInternAtom(
-                self.display,
-                CString::new("_NET_WM_NAME").unwrap().as_ptr(),
-                0,
-            );
-            let utf8 = XInternAtom(
-                self.display,
-                CString::new("UTF8_STRING").unwrap().as_ptr(),
-                0,
-            );
+        // window was spawned for a scratchpad
+        self.pending_scratchpads
+            .retain(|_, started| started.elapsed() < LAUNCH_TIMEOUT);
+        let pending = self
+            .pending_scratchpads
+            .keys()
+            .find(|name| self.scratchpad_matches(name, window))
+            .cloned();
+        if let Some(name) = pending {
+            self.pending_scratchpads.remove(&name);
+            self.named_scratchpads.insert(name, window);
+            self.scratchpad.insert(window);
+            // so it is found again after a restart, even while hidden
+            self.set_wm_state(window, NORMAL_STATE);
+            unsafe { xlib::XSelectInput(self.display, window, xlib::PropertyChangeMask) };
+            self.show_scratchpad(window);
+            return;
+        }
 
-            let mut actual_type: Atom = std::mem::zeroed();
-            let mut actual_format: i32 = std::mem::zeroed();
-            let mut nitems: u64 = std::mem::zeroed();
-            let mut bytes_after: u64 = std::mem::zeroed();
-            let mut data: *mut u8 = std::ptr::null_mut();
+        let props = self.get_window_props(window);
+        let mut rule = apply_rules(&self.rules, &props);
 
-            if XGetWindowProperty(
-                self.display,
-                window,
-                net_wm_name,
-                0,
-                !0,
-                0,
-                utf8,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut data,
-            ) == 0
-                && !data.is_null()
-            {
-                let win_title = std::ffi::CStr::from_ptr(data as *const i8)
-                    .to_string_lossy()
-                    .into_owned();
-                xlib::XFree(data as *mut _);
-                return Ok(win_title);
+        // window was relaunched by session_restore
+        if let Some(pos) = self.pending_launch_of(window, &props) {
+            let saved = self.pending_session.remove(pos).saved;
+            rule.desktop = Some(saved.desktop);
+            rule.floating = Some(saved.floating);
+            if saved.floating {
+                rule.geometry = saved.geometry;
             }
         }
+        let sticky_atom = self.get_atom("_NET_WM_STATE_STICKY");
+        let sticky = rule
+            .sticky
+            .unwrap_or_else(|| self.get_net_wm_state(window).contains(&sticky_atom));
+
+        // new windows open on the monitor under the pointer,
+        // desktops are numbered from 1 in the config
+        let pointer_desktop = self.monitors[self.pointer_monitor()].desktop;
+        let index = match (desktop, rule.desktop) {
+            _ if sticky => pointer_desktop,
+            (Some(index), _) => index,
+            (None, Some(num)) => num.saturating_sub(1),
+            (None, None) => pointer_desktop,
+        };
+        let mut desktop = self.get_desktop(index);
+        desktop.insert(window);
+        self.set_desktop(index, desktop);
 
-        Err(GridWMError::Other(format!(
-            "failed to get name for window {}",
-            window
-        )))
+        if rule.floating.unwrap_or(rule.geometry.is_some()) {
+            self.floating_windows.insert(window);
+        }
+        if let Some([x, y, w, h]) = rule.geometry {
+            self.move_resize_window(window, x, y, w.max(1) as u32, h.max(1) as u32);
+        }
+        if rule.no_title_bar == Some(true) {
+            self.no_title_bar.insert(window);
+        }
+        if rule.fullscreen == Some(true) {
+            self.set_fullscreen(window);
+        }
+        if rule.focus == Some(false) {
+            self.skip_focus.insert(window);
+        }
+        if sticky {
+            self.set_sticky(window, true);
+        }
+        if (self.floating_windows.contains(&window) || self.is_dialog(window))
+            && rule.geometry.is_none()
+            && rule.fullscreen != Some(true)
+            && !adopted
+            && !self.has_user_position(window)
+        {
+            let policy = rule
+                .placement
+                .clone()
+                .unwrap_or_else(|| self.config.window.placement.clone());
+            self.place_window(window, &policy);
+        }
+        if rule.terminal == Some(true) {
+            self.terminals.insert(window);
+        }
+        // adopted windows already had their chance, restarts restore what was swallowed
+        if !adopted
+            && rule.no_swallow != Some(true)
+            && !self.floating_windows.contains(&window)
+            && self.is_tileable(window)
+            && let Some(terminal) = self.find_swallower(window, index)
+        {
+            self.swallow(terminal, window);
+        }
+
+        self.set_wm_state(window, NORMAL_STATE);
+        // urgency and title changes
+        unsafe { xlib::XSelectInput(self.display, window, xlib::PropertyChangeMask) };
+
+        // windows for other desktops get mapped when switching to them
+        if self.is_shown(index) {
+            unsafe { xlib::XMapWindow(self.display, window) };
+        } else {
+            self.skip_focus.remove(&window);
+            // adopted windows may already be visible
+            self.unmap_window(window);
+        }
     }
 
-    // may be used later on
-    fn _get_focused(&self) -> Option<Window> {
+    // manages windows that were mapped before gridwm started
+    fn adopt_windows(&mut self) {
+        let root = unsafe { XDefaultRootWindow(self.display) };
+        let mut windows = Vec::new();
         unsafe {
-            let mut focused: Window = std::mem::zeroed();
-            let mut revert: i32 = std::mem::zeroed();
-            xlib::XGetInputFocus(
+            let mut root_return: Window = 0;
+            let mut parent: Window = 0;
+            let mut children: *mut Window = std::ptr::null_mut();
+            let mut nchildren: u32 = 0;
+            if xlib::XQueryTree(
                 self.display,
-                &mut focused as *mut Window,
-                &mut revert as *mut i32,
-            );
-            if focused == 0 || focused == xlib::PointerRoot as u64 {
-                None
-            } else {
-                Some(focused)
+                root,
+                &mut root_return,
+                &mut parent,
+                &mut children,
+                &mut nchildren,
+            ) == 0
+            {
+                return;
+            }
+            if !children.is_null() {
+                windows.extend_from_slice(std::slice::from_raw_parts(children, nchildren as usize));
+                xlib::XFree(children as *mut _);
             }
         }
-    }
-
-    fn remove_window(&mut self, event: xlib::XEvent) {
-        let event: xlib::XUnmapEvent = From::from(event);
-        let mut desktop = self.get_desktop(self.current_desktop);
-        desktop.remove(&event.window);
-        self.set_desktop(self.current_desktop, desktop);
-        self.floating_windows.remove(&event.window);
 
-        if let Some(bar_win) = self.win_bar_windows.remove(&event.window) {
-            unsafe {
-                xlib::XDestroyWindow(self.display, bar_win);
-            }
+        let state = self.restart_state.take().unwrap_or_default();
+        for (name, window) in &state.named_scratchpads {
+            self.named_scratchpads.insert(name.clone(), *window);
         }
-    }
 
-    fn move_window(&mut self, window: Window, x: i32, y: i32) {
+        for window in windows {
+            let mut attrs: xlib::XWindowAttributes = unsafe { std::mem::zeroed() };
+            if unsafe { xlib::XGetWindowAttributes(self.display, window, &mut attrs) } == 0
+                || attrs.override_redirect != 0
+            {
+                continue;
+            }
+            // swallowed terminals stay hidden behind their window, see below
+            if state
+                .swallowed
+                .iter()
+                .any(|&(_, terminal)| terminal == window)
+            {
+                continue;
+            }
+            // windows the old gridwm knew are taken over even if hidden,
+            // other unmapped ones were hidden by us if they still have a WM_STATE
+            let saved_desktop = state.desktops.iter().position(|d| d.contains(&window));
+            let known = saved_desktop.is_some() || state.scratchpad.contains(&window);
+            let wm_state = self.get_wm_state(window);
+            if !known
+                && attrs.map_state != xlib::IsViewable
+                && wm_state != Some(NORMAL_STATE)
+                && wm_state != Some(ICONIC_STATE)
+            {
+                continue;
+            }
+
+            if state.scratchpad.contains(&window) {
+                self.scratchpad.insert(window);
+                // hidden scratchpads stay hidden
+                if saved_desktop.is_none() {
+                    continue;
+                }
+            }
+
+            info!("adopting window {}", window);
+            if state.floating.contains(&window) {
+                self.floating_windows.insert(window);
+            }
+            if let Some(g) = state.float_geometry.iter().find(|g| g.window == window) {
+                self.float_geometry.insert(
+                    window,
+                    WindowInfo {
+                        x: g.x,
+                        y: g.y,
+                        w: g.w,
+                        h: g.h,
+                    },
+                );
+            }
+            let desktop = saved_desktop.or_else(|| {
+                self.get_cardinal(window, "_NET_WM_DESKTOP")
+                    .filter(|&d| d != 0xFFFFFFFF)
+                    .map(|d| d as usize)
+            });
+            self.manage(window, desktop, true);
+
+            if state.minimized.contains(&window) || wm_state == Some(ICONIC_STATE) {
+                self.minimize(window);
+            }
+        }
+        self.named_scratchpads
+            .retain(|_, window| self.scratchpad.contains(window));
+
+        for &(window, terminal) in &state.swallowed {
+            if !self.window_exists(terminal) {
+                continue;
+            }
+            if self.desktops.iter().any(|d| d.contains(&window)) {
+                self.swallowed.insert(window, terminal);
+                self.unmap_window(terminal);
+            } else {
+                // its window is gone, so the terminal comes back
+                let desktop = self
+                    .get_cardinal(terminal, "_NET_WM_DESKTOP")
+                    .filter(|&d| d != 0xFFFFFFFF)
+                    .map(|d| d as usize);
+                self.manage(terminal, desktop, true);
+            }
+        }
+
+        for saved in state.tab_groups {
+            let active = saved.windows.get(saved.active).copied();
+            let windows: Vec<Window> = saved
+                .windows
+                .into_iter()
+                .filter(|&w| self.desktops.iter().any(|d| d.contains(&w)))
+                .collect();
+            // a single window left is not a group anymore
+            if windows.len() < 2 {
+                continue;
+            }
+            let Some(active) = active
+                .filter(|w| windows.contains(w))
+                .or(windows.first().copied())
+            else {
+                continue;
+            };
+            for &window in windows.iter().filter(|&&w| w != active) {
+                self.hide_tab(window);
+            }
+            self.tab_groups.push(TabGroup {
+                active: windows.iter().position(|&w| w == active).unwrap_or(0),
+                windows,
+            });
+        }
+
+        self.layout();
+        match state.focused {
+            Some(window) if self.is_visible(window) => self.focus(window),
+            _ if self.focused_client().is_none() => self.focus_fallback(),
+            _ => {}
+        }
+    }
+
+    // saves what can't be read back from the windows and execs gridwm again
+    fn restart(&mut self) {
+        let Some(program) = std::env::args().next() else {
+            error!("failed to restart: unknown program name");
+            return;
+        };
+
+        let state = state::RestartState {
+            monitor_desktops: self.monitors.iter().map(|m| m.desktop).collect(),
+            current_monitor: self.current_monitor,
+            focused: self.focused,
+            desktops: self
+                .desktops
+                .iter()
+                .map(|d| d.iter().copied().collect())
+                .collect(),
+            floating: self.floating_windows.iter().copied().collect(),
+            float_geometry: self
+                .float_geometry
+                .iter()
+                .map(|(&window, g)| state::SavedGeometry {
+                    window,
+                    x: g.x,
+                    y: g.y,
+                    w: g.w,
+                    h: g.h,
+                })
+                .collect(),
+            minimized: self.minimized.clone(),
+            scratchpad: self.scratchpad.iter().copied().collect(),
+            named_scratchpads: self
+                .named_scratchpads
+                .iter()
+                .map(|(name, &window)| (name.clone(), window))
+                .collect(),
+            swallowed: self
+                .swallowed
+                .iter()
+                .map(|(&window, &terminal)| (window, terminal))
+                .collect(),
+            tab_groups: self
+                .tab_groups
+                .iter()
+                .map(|g| state::SavedTabGroup {
+                    windows: g.windows.clone(),
+                    active: g.active,
+                })
+                .collect(),
+        };
+        if let Err(e) = state::save(&self.display_name, &state) {
+            error!("failed to save state for restart: {}", e);
+            return;
+        }
+
+        info!("restarting gridwm");
+        // the new process has to open its own connection to get the redirect
+        unsafe { xlib::XCloseDisplay(self.display) };
+        let e = Command::new(&program).args(std::env::args().skip(1)).exec();
+        error!("failed to restart {}: {}", program, e);
+        std::process::exit(1);
+    }
+
+    fn session_path(path: Option<&str>) -> Option<PathBuf> {
+        let path = match path {
+            Some(path) => match (path.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => Some(home.join(rest)),
+                _ => Some(PathBuf::from(path)),
+            },
+            None => session::default_path(),
+        };
+        if path.is_none() {
+            error!("failed to find a place for the session file");
+        }
+        path
+    }
+
+    fn session_save(&mut self, path: Option<&str>) {
+        let Some(path) = Self::session_path(path) else {
+            return;
+        };
+
+        let mut windows = Vec::new();
+        for (index, desktop) in self.desktops.iter().enumerate() {
+            for &window in desktop {
+                // scratchpads are started from the config
+                if self.scratchpad.contains(&window) {
+                    continue;
+                }
+                let props = self.get_window_props(window);
+                let command = self
+                    .get_pid(window)
+                    .and_then(process::cmdline)
+                    .map(|args| shell_words::join(&args))
+                    .unwrap_or_default();
+                let floating = self.floating_windows.contains(&window);
+                let geometry = floating.then(|| {
+                    let attrs = self.get_window_attributes(window);
+                    [attrs.x, attrs.y, attrs.width, attrs.height]
+                });
+                windows.push(session::SessionWindow {
+                    class: props.class,
+                    instance: props.instance,
+                    title: props.title,
+                    command,
+                    desktop: index + 1,
+                    floating,
+                    geometry,
+                });
+            }
+        }
+
+        match session::save(&path, &session::Session { windows }) {
+            Ok(_) => info!("saved session to {}", path.display()),
+            Err(e) => error!("failed to save session to {}: {}", path.display(), e),
+        }
+    }
+
+    // puts running windows back in place and relaunches missing ones
+    fn session_restore(&mut self, path: Option<&str>) {
+        let Some(path) = Self::session_path(path) else {
+            return;
+        };
+        let session = match session::load(&path) {
+            Ok(session) => session,
+            Err(e) => {
+                error!("failed to load session from {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        let mut running: Vec<(Window, WindowProps)> = self
+            .desktops
+            .iter()
+            .flatten()
+            .filter(|w| !self.scratchpad.contains(w))
+            .map(|&w| (w, self.get_window_props(w)))
+            .collect();
+
+        for saved in session.windows {
+            if let Some(pos) = running.iter().position(|(_, props)| saved.matches(props)) {
+                let (window, _) = running.remove(pos);
+                self.restore_session_window(window, &saved);
+            } else if saved.command.is_empty() {
+                warn!("can't relaunch {}: unknown command", saved.class);
+            } else {
+                match spawn(&saved.command) {
+                    // placed when it maps
+                    Ok(pid) => self.pending_session.push(PendingLaunch {
+                        saved,
+                        pid,
+                        started: Instant::now(),
+                    }),
+                    Err(e) => error!("failed to run '{}': {}", saved.command, e),
+                }
+            }
+        }
+
+        self.layout();
+        self.trigger_redraw = true;
+    }
+
+    // the relaunched program a new window belongs to, by process or by class
+    // if the window doesn't tell its pid
+    fn pending_launch_of(&mut self, window: Window, props: &WindowProps) -> Option<usize> {
+        self.pending_session
+            .retain(|launch| launch.started.elapsed() < LAUNCH_TIMEOUT);
+        if self.pending_session.is_empty() {
+            return None;
+        }
+
+        match self.get_pid(window) {
+            Some(pid) => {
+                let ancestors = process::ancestors(pid);
+                self.pending_session
+                    .iter()
+                    .position(|launch| launch.pid == pid || ancestors.contains(&launch.pid))
+            }
+            None => self
+                .pending_session
+                .iter()
+                .position(|launch| launch.saved.matches(props)),
+        }
+    }
+
+    fn restore_session_window(&mut self, window: Window, saved: &session::SessionWindow) {
+        self.send_to_desktop(window, saved.desktop.saturating_sub(1));
+        if saved.floating {
+            self.float_window(window);
+            if let Some([x, y, w, h]) = saved.geometry {
+                self.move_resize_window(window, x, y, w.max(1) as u32, h.max(1) as u32);
+            }
+        } else if self.floating_windows.contains(&window) {
+            self.unfloat_window(window);
+        }
+    }
+
+    fn send_to_desktop(&mut self, window: Window, index: usize) {
+        if self.sticky.contains(&window) || self.desktop_of(window) == index {
+            return;
+        }
+        self.leave_tab_group(window);
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&window);
+        }
+        let mut desktop = self.get_desktop(index);
+        desktop.insert(window);
+        self.set_desktop(index, desktop);
+
+        if self.minimized.contains(&window) {
+            return;
+        }
+        if self.is_shown(index) {
+            self.map_window(window);
+        } else {
+            self.unmap_window(window);
+            if self.focused == Some(window) {
+                self.focus_fallback();
+            }
+        }
+    }
+
+    fn is_viewable(&self, window: Window) -> bool {
+        let mut attrs: xlib::XWindowAttributes = unsafe { std::mem::zeroed() };
+        let ok = unsafe { xlib::XGetWindowAttributes(self.display, window, &mut attrs) };
+        ok != 0 && attrs.map_state == xlib::IsViewable
+    }
+
+    fn is_dialog(&self, window: Window) -> bool {
+        let mut transient_for: Window = 0;
+        let transient =
+            unsafe { xlib::XGetTransientForHint(self.display, window, &mut transient_for) != 0 };
+        transient || self.get_window_type(window).as_deref() == Some("_NET_WM_WINDOW_TYPE_DIALOG")
+    }
+
+    // position chosen by the user, e.g. with a -geometry argument
+    fn has_user_position(&self, window: Window) -> bool {
+        unsafe {
+            let mut hints: xlib::XSizeHints = zeroed();
+            let mut supplied: i64 = 0;
+            xlib::XGetWMNormalHints(self.display, window, &mut hints, &mut supplied) != 0
+                && hints.flags & xlib::USPosition != 0
+        }
+    }
+
+    // puts a new floating window on the screen according to a placement policy
+    fn place_window(&mut self, window: Window, policy: &str) {
+        let attrs = self.get_window_attributes(window);
+        let area = self.work_area(self.monitor_of(window));
+        let top_offset = self.title_bar_height(window);
+        let w = attrs.width;
+        let h = attrs.height;
+
+        let (x, y) = match policy {
+            "under_mouse" => {
+                let (_, pointer_x, pointer_y, _) = self.query_pointer();
+                (pointer_x - w / 2, pointer_y - h / 2)
+            }
+            "cascade" => {
+                const STEP: i32 = 30;
+                let count = self
+                    .get_desktop(self.desktop_of(window))
+                    .iter()
+                    .filter(|&&other| other != window && self.floating_windows.contains(&other))
+                    .count() as i32;
+                // start over at the top left when running out of space
+                let steps = ((area.w - w) / STEP)
+                    .min((area.h - top_offset - h) / STEP)
+                    .max(0)
+                    + 1;
+                let offset = (count % steps) * STEP;
+                (area.x + offset, area.y + top_offset + offset)
+            }
+            "smart" => self.smart_position(window, w, h, top_offset),
+            other => {
+                if other != "center" {
+                    warn!("unknown placement \"{}\", using center", other);
+                }
+                (
+                    area.x + (area.w - w) / 2,
+                    area.y + top_offset + (area.h - top_offset - h) / 2,
+                )
+            }
+        };
+
+        let geometry = self.clamp_to_area(window, WindowInfo { x, y, w, h });
+        self.move_resize_window(
+            window,
+            geometry.x,
+            geometry.y,
+            geometry.w as u32,
+            geometry.h as u32,
+        );
+    }
+
+    // position with the least overlap with other floating windows
+    fn smart_position(&self, window: Window, w: i32, h: i32, top_offset: i32) -> (i32, i32) {
+        let area = self.work_area(self.monitor_of(window));
+        let others: Vec<WindowInfo> = self
+            .get_desktop(self.desktop_of(window))
+            .iter()
+            .copied()
+            .filter(|&other| {
+                other != window
+                    && self.floating_windows.contains(&other)
+                    && !self.minimized.contains(&other)
+            })
+            .map(|other| {
+                let attrs = self.get_window_attributes(other);
+                let other_top = self.title_bar_height(other);
+                WindowInfo {
+                    x: attrs.x,
+                    y: attrs.y - other_top,
+                    w: attrs.width,
+                    h: attrs.height + other_top,
+                }
+            })
+            .collect();
+
+        let full_h = h + top_offset;
+        let mut xs = vec![area.x, area.x + area.w - w];
+        let mut ys = vec![area.y, area.y + area.h - full_h];
+        for other in &others {
+            xs.push(other.x + other.w);
+            xs.push(other.x - w);
+            ys.push(other.y + other.h);
+            ys.push(other.y - full_h);
+        }
+
+        let mut best = (i64::MAX, area.x, area.y);
+        for &y in &ys {
+            for &x in &xs {
+                if x < area.x
+                    || y < area.y
+                    || x + w > area.x + area.w
+                    || y + full_h > area.y + area.h
+                {
+                    continue;
+                }
+                let overlap: i64 = others
+                    .iter()
+                    .map(|o| {
+                        let ow = (x + w).min(o.x + o.w) - x.max(o.x);
+                        let oh = (y + full_h).min(o.y + o.h) - y.max(o.y);
+                        ow.max(0) as i64 * oh.max(0) as i64
+                    })
+                    .sum();
+                if overlap < best.0 {
+                    best = (overlap, x, y);
+                }
+            }
+        }
+
+        (best.1, best.2 + top_offset)
+    }
+
+    // keeps a floating window (and its title bar) inside the work area
+    fn clamp_to_area(&self, window: Window, geometry: WindowInfo) -> WindowInfo {
+        // the monitor the window is mostly on
+        let monitor = self
+            .monitor_at(geometry.x + geometry.w / 2, geometry.y + geometry.h / 2)
+            .unwrap_or_else(|| self.monitor_of(window));
+        let area = self.work_area(monitor);
+        let top_offset = self.title_bar_height(window);
+        let w = geometry.w.min(area.w).max(1);
+        let h = geometry.h.min(area.h - top_offset).max(1);
+        WindowInfo {
+            x: geometry.x.clamp(area.x, area.x + area.w - w),
+            y: geometry.y.clamp(area.y + top_offset, area.y + area.h - h),
+            w,
+            h,
+        }
+    }
+
+    fn set_fullscreen(&mut self, window: Window) {
+        self.floating_windows.insert(window);
+        self.no_title_bar.insert(window);
+        self.set_net_wm_state(window, "_NET_WM_STATE_FULLSCREEN", true);
+        let area = self.monitors[self.monitor_of(window)].area;
+        let border = self.border_width(window);
+        unsafe { xlib::XSetWindowBorderWidth(self.display, window, border) };
+        let border = 2 * border as i32;
+        self.move_resize_window(
+            window,
+            area.x,
+            area.y,
+            (area.w - border).max(1) as u32,
+            (area.h - border).max(1) as u32,
+        );
+        unsafe { xlib::XRaiseWindow(self.display, window) };
+    }
+
+    // terminal on the desktop that (indirectly) started the window
+    fn find_swallower(&self, window: Window, index: usize) -> Option<Window> {
+        let pid = self.get_pid(window)?;
+        let terminals: Vec<(Window, u32)> = self
+            .get_desktop(index)
+            .iter()
+            .filter(|w| self.terminals.contains(w) && !self.swallowed.contains_key(w))
+            .filter_map(|&w| self.get_pid(w).map(|p| (w, p)))
+            .collect();
+
+        process::ancestors(pid).into_iter().find_map(|parent| {
+            terminals
+                .iter()
+                .find(|(_, terminal_pid)| *terminal_pid == parent)
+                .map(|(w, _)| *w)
+        })
+    }
+
+    fn swallow(&mut self, terminal: Window, window: Window) {
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&terminal);
+        }
+        if let Some(bar_win) = self.win_bar_windows.remove(&terminal) {
+            unsafe { xlib::XDestroyWindow(self.display, bar_win) };
+        }
+        self.swallowed.insert(window, terminal);
+        self.unmap_window(terminal);
+    }
+
+    // gives the slot of a closed window back to the terminal it swallowed
+    fn unswallow(&mut self, window: Window, index: usize) {
+        let Some(terminal) = self.swallowed.remove(&window) else {
+            return;
+        };
+        if !self.window_exists(terminal) {
+            return;
+        }
+
+        let mut desktop = self.get_desktop(index);
+        desktop.insert(terminal);
+        self.set_desktop(index, desktop);
+        if self.is_shown(index) {
+            self.map_window(terminal);
+        }
+    }
+
+    fn get_wm_state(&self, window: Window) -> Option<i64> {
+        let wm_state = self.get_atom("WM_STATE");
+        self.get_long(window, wm_state, wm_state)
+            .map(|state| state as i64)
+    }
+
+    fn get_pid(&self, window: Window) -> Option<u32> {
+        self.get_cardinal(window, "_NET_WM_PID")
+            .map(|pid| pid as u32)
+    }
+
+    fn get_cardinal(&self, window: Window, name: &str) -> Option<u64> {
+        self.get_long(window, self.get_atom(name), xlib::XA_CARDINAL)
+    }
+
+    // first 32 bit value of a property
+    fn get_long(&self, window: Window, property: Atom, kind: Atom) -> Option<u64> {
+        unsafe {
+            let mut actual_type: Atom = 0;
+            let mut actual_format: i32 = 0;
+            let mut nitems: u64 = 0;
+            let mut bytes_after: u64 = 0;
+            let mut prop: *mut u8 = std::ptr::null_mut();
+
+            if XGetWindowProperty(
+                self.display,
+                window,
+                property,
+                0,
+                1,
+                0,
+                kind,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut prop,
+            ) != 0
+                || prop.is_null()
+            {
+                return None;
+            }
+
+            let value = if nitems > 0 {
+                Some(*(prop as *const u64))
+            } else {
+                None
+            };
+            xlib::XFree(prop as *mut _);
+            value
+        }
+    }
+
+    // sticky windows float and stay visible on every desktop
+    fn set_sticky(&mut self, window: Window, sticky: bool) {
+        if sticky {
+            self.sticky.insert(window);
+            self.floating_windows.insert(window);
+        } else {
+            self.sticky.remove(&window);
+        }
+        let desktop = if sticky {
+            0xFFFFFFFF
+        } else {
+            self.desktop_of(window) as u64
+        };
+        self.set_cardinal(window, "_NET_WM_DESKTOP", desktop);
+        self.set_net_wm_state(window, "_NET_WM_STATE_STICKY", sticky);
+        self.layout();
+    }
+
+    fn minimize(&mut self, window: Window) {
+        if self.minimized.contains(&window) || !self.desktops.iter().any(|d| d.contains(&window)) {
+            return;
+        }
+        self.leave_tab_group(window);
+        self.minimized.push(window);
+
+        self.set_wm_state(window, ICONIC_STATE);
+        self.set_net_wm_state(window, "_NET_WM_STATE_HIDDEN", true);
+        if let Some(bar_win) = self.win_bar_windows.remove(&window) {
+            unsafe { xlib::XDestroyWindow(self.display, bar_win) };
+        }
+        self.unmap_window(window);
+        self.layout();
+        self.trigger_redraw = true;
+
+        if self.focused == Some(window) {
+            self.focus_fallback();
+        }
+    }
+
+    // brings a minimized window back on the current desktop
+    fn restore(&mut self, window: Window) {
+        let Some(pos) = self.minimized.iter().position(|&w| w == window) else {
+            return;
+        };
+        self.minimized.remove(pos);
+
+        if !self.window_exists(window) {
+            return;
+        }
+
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&window);
+        }
+        let mut desktop = self.get_desktop(self.current_desktop());
+        desktop.insert(window);
+        self.set_desktop(self.current_desktop(), desktop);
+
+        self.set_wm_state(window, NORMAL_STATE);
+        self.set_net_wm_state(window, "_NET_WM_STATE_HIDDEN", false);
+        self.map_window(window);
+        unsafe { xlib::XRaiseWindow(self.display, window) };
+        self.layout();
+        self.focus(window);
+    }
+
+    fn open_restore_picker(&mut self) {
+        if self.minimized.is_empty() {
+            return;
+        }
+
+        let windows: Vec<Window> = self.minimized.iter().rev().copied().collect();
+        let titles: Vec<String> = windows
+            .iter()
+            .map(|&w| self.get_name(w).unwrap_or_default())
+            .collect();
+        self.open_picker(PickerAction::Restore(windows), titles);
+    }
+
+    // shows the menu without blocking, the choice is handled in the main loop
+    fn open_picker(&mut self, action: PickerAction, items: Vec<String>) -> bool {
+        if self.picker.is_some() {
+            return false;
+        }
+
+        let command = self.config.general.menu.clone();
+        let (tx, rx) = mpsc::channel();
+        self.picker = Some((action, rx));
+        let waker = self.wakeup.waker();
+        thread::spawn(move || {
+            let _ = tx.send(pick(&command, &items));
+            waker.wake();
+        });
+        true
+    }
+
+    fn handle_pick(&mut self, action: PickerAction, choice: usize) {
+        match action {
+            PickerAction::Restore(windows) => {
+                if let Some(&window) = windows.get(choice) {
+                    self.restore(window);
+                }
+            }
+            PickerAction::Kill(window) => {
+                // first entry is "kill", second "wait"
+                if choice == 0 && self.window_exists(window) {
+                    self.kill_window(window);
+                }
+            }
+        }
+    }
+
+    // asks the window to close, kills it if it doesn't support that
+    fn close_window(&mut self, window: Window) {
+        if has_protocol(self.display, window, "WM_DELETE_WINDOW") {
+            send_wm_delete_window(self.display, window);
+            if has_protocol(self.display, window, "_NET_WM_PING") {
+                send_net_wm_ping(self.display, window);
+            }
+            self.pending_closes.insert(window, (Instant::now(), false));
+        } else {
+            unsafe { xlib::XKillClient(self.display, window) };
+        }
+    }
+
+    fn kill_window(&mut self, window: Window) {
+        self.pending_closes.remove(&window);
+
+        // only kill the process if it is known to run on this machine
+        let local = match (
+            self.get_text_property(window, "WM_CLIENT_MACHINE"),
+            process::hostname(),
+        ) {
+            (Some(machine), Some(host)) => machine == host,
+            _ => false,
+        };
+        if local && let Some(pid) = self.get_pid(window).filter(|&pid| pid > 1) {
+            unsafe { libc::kill(pid as i32, libc::SIGKILL) };
+        }
+
+        unsafe { xlib::XKillClient(self.display, window) };
+    }
+
+    // offers to kill windows that ignored a close request
+    fn check_pending_closes(&mut self) {
+        let timeout = self.config.window.close_timeout;
+        if timeout <= 0.0 || self.pending_closes.is_empty() {
+            return;
+        }
+
+        let expired: Vec<Window> = self
+            .pending_closes
+            .iter()
+            .filter(|(_, (requested, answered))| {
+                !answered && requested.elapsed().as_secs_f32() >= timeout
+            })
+            .map(|(&window, _)| window)
+            .collect();
+
+        for window in expired {
+            if !self.window_exists(window) {
+                self.pending_closes.remove(&window);
+                continue;
+            }
+
+            let title = self.get_name(window).unwrap_or_default();
+            let items = vec![
+                format!("Kill \"{}\" (not responding)", title),
+                "Wait".to_string(),
+            ];
+            // try again later if another menu is open
+            if self.open_picker(PickerAction::Kill(window), items) {
+                self.pending_closes.remove(&window);
+            }
+        }
+    }
+
+    // time until the first unanswered close request should be offered to be killed
+    fn next_close_timeout(&self) -> Option<Duration> {
+        let timeout = self.config.window.close_timeout;
+        if timeout <= 0.0 {
+            return None;
+        }
+        self.pending_closes
+            .values()
+            .filter(|(_, answered)| !answered)
+            .map(|(requested, _)| {
+                Duration::from_secs_f32(timeout).saturating_sub(requested.elapsed())
+            })
+            .min()
+    }
+
+    // minimum time between redraws of the bars, follows the refresh rate if unset
+    fn redraw_throttle(&self) -> Duration {
+        match self.update_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None => Duration::from_millis((1000.0 / self.refresh_rate.max(1) as f64) as u64),
+        }
+    }
+
+    fn handle_client_message(&mut self, event: xlib::XClientMessageEvent) {
+        // answer to _NET_WM_PING, the client is still responding
+        if event.message_type == self.get_atom("WM_PROTOCOLS")
+            && event.data.get_long(0) as Atom == self.get_atom("_NET_WM_PING")
+        {
+            let window = event.data.get_long(2) as Window;
+            if let Some((_, answered)) = self.pending_closes.get_mut(&window) {
+                *answered = true;
+            }
+        } else if event.message_type == self.get_atom("WM_CHANGE_STATE")
+            && event.data.get_long(0) == ICONIC_STATE
+        {
+            self.minimize(event.window);
+        } else if event.message_type == self.get_atom("_NET_WM_STATE") {
+            if !self.desktops.iter().any(|d| d.contains(&event.window)) {
+                return;
+            }
+
+            // remove, add or toggle up to two properties
+            let action = event.data.get_long(0);
+            let properties = [
+                event.data.get_long(1) as Atom,
+                event.data.get_long(2) as Atom,
+            ];
+
+            if properties.contains(&self.get_atom("_NET_WM_STATE_STICKY")) {
+                let sticky = match action {
+                    0 => false,
+                    1 => true,
+                    _ => !self.sticky.contains(&event.window),
+                };
+                self.set_sticky(event.window, sticky);
+            }
+        }
+    }
+
+    fn set_wm_state(&self, window: Window, state: i64) {
+        let wm_state = self.get_atom("WM_STATE");
+        // state and icon window
+        let data: [i64; 2] = [state, 0];
+        unsafe {
+            xlib::XChangeProperty(
+                self.display,
+                window,
+                wm_state,
+                wm_state,
+                32,
+                xlib::PropModeReplace,
+                data.as_ptr() as *const u8,
+                2,
+            );
+        }
+    }
+
+    fn get_net_wm_state(&self, window: Window) -> Vec<Atom> {
+        unsafe {
+            let mut actual_type: Atom = 0;
+            let mut actual_format: i32 = 0;
+            let mut nitems: u64 = 0;
+            let mut bytes_after: u64 = 0;
+            let mut prop: *mut u8 = std::ptr::null_mut();
+
+            if XGetWindowProperty(
+                self.display,
+                window,
+                self.get_atom("_NET_WM_STATE"),
+                0,
+                !0,
+                0,
+                xlib::XA_ATOM,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut prop,
+            ) != 0
+                || prop.is_null()
+            {
+                return Vec::new();
+            }
+
+            let atoms = slice::from_raw_parts(prop as *const Atom, nitems as usize).to_vec();
+            xlib::XFree(prop as *mut _);
+            atoms
+        }
+    }
+
+    fn set_net_wm_state(&self, window: Window, state: &str, enable: bool) {
+        let state = self.get_atom(state);
+        let mut atoms = self.get_net_wm_state(window);
+        atoms.retain(|&a| a != state);
+        if enable {
+            atoms.push(state);
+        }
+
+        unsafe {
+            xlib::XChangeProperty(
+                self.display,
+                window,
+                self.get_atom("_NET_WM_STATE"),
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                atoms.as_ptr() as *const u8,
+                atoms.len() as i32,
+            );
+        }
+    }
+
+    // unmaps a window without it being treated as closed
+    // maps a window that was hidden by us, focus is up to the caller
+    fn map_window(&mut self, window: Window) {
+        // mapping a visible window sends no MapNotify
+        if self.is_viewable(window) {
+            return;
+        }
+        *self.ignore_maps.entry(window).or_insert(0) += 1;
+        unsafe { xlib::XMapWindow(self.display, window) };
+    }
+
+    // whether a MapNotify came from map_window
+    fn take_ignored_map(&mut self, window: Window) -> bool {
+        let Some(count) = self.ignore_maps.get_mut(&window) else {
+            return false;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.ignore_maps.remove(&window);
+        }
+        true
+    }
+
+    fn unmap_window(&mut self, window: Window) {
+        // unmapping a hidden window sends no UnmapNotify
+        if !self.is_viewable(window) {
+            return;
+        }
+        *self.ignore_unmaps.entry(window).or_insert(0) += 1;
+        unsafe { xlib::XUnmapWindow(self.display, window) };
+    }
+
+    fn get_atom(&self, name: &str) -> Atom {
+        let name = CString::new(name).unwrap();
+        unsafe { XInternAtom(self.display, name.as_ptr(), 0) }
+    }
+
+    fn get_text_property(&self, window: Window, name: &str) -> Option<String> {
+        unsafe {
+            let mut prop: xlib::XTextProperty = zeroed();
+            if xlib::XGetTextProperty(self.display, window, &mut prop, self.get_atom(name)) == 0
+                || prop.value.is_null()
+            {
+                return None;
+            }
+            let text = std::ffi::CStr::from_ptr(prop.value as *const i8)
+                .to_string_lossy()
+                .into_owned();
+            xlib::XFree(prop.value as *mut _);
+            Some(text)
+        }
+    }
+
+    fn get_window_type(&self, window: Window) -> Option<String> {
+        unsafe {
+            let mut actual_type: Atom = 0;
+            let mut actual_format: i32 = 0;
+            let mut nitems: u64 = 0;
+            let mut bytes_after: u64 = 0;
+            let mut prop: *mut u8 = std::ptr::null_mut();
+
+            if XGetWindowProperty(
+                self.display,
+                window,
+                self.get_atom("_NET_WM_WINDOW_TYPE"),
+                0,
+                1,
+                0,
+                xlib::XA_ATOM,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut prop,
+            ) != 0
+                || prop.is_null()
+            {
+                return None;
+            }
+
+            let wtype = if nitems > 0 {
+                Some(*(prop as *const Atom))
+            } else {
+                None
+            };
+            xlib::XFree(prop as *mut _);
+
+            let name = xlib::XGetAtomName(self.display, wtype?);
+            if name.is_null() {
+                return None;
+            }
+            let type_name = std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned();
+            xlib::XFree(name as *mut _);
+            Some(type_name)
+        }
+    }
+
+    fn get_window_props(&self, window: Window) -> WindowProps {
+        let (instance, class) = self.get_class(window).unwrap_or_default();
+        WindowProps {
+            instance,
+            class,
+            role: self
+                .get_text_property(window, "WM_WINDOW_ROLE")
+                .unwrap_or_default(),
+            window_type: self.get_window_type(window).unwrap_or_default(),
+            title: self.get_name(window).unwrap_or_default(),
+        }
+    }
+
+    fn get_name(&self, window: Window) -> Result<String, GridWMError> {
+        let mut win_name: *mut i8 = std::ptr::null_mut();
+        let status = unsafe { xlib::XFetchName(self.display, window, &mut win_name) };
+        if status != 0 && !win_name.is_null() {
+            let win_title = unsafe {
+                std::ffi::CStr::from_ptr(win_name)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            unsafe { xlib::XFree(win_name as *mut _) };
+            return Ok(win_title);
+        }
+
+        // if above fails
+        unsafe {
+            let net_wm_name = XInternAtom(
+                self.display,
+                CString::new("_NET_WM_NAME").unwrap().as_ptr(),
+                0,
+            );
+            let utf8 = XInternAtom(
+                self.display,
+                CString::new("UTF8_STRING").unwrap().as_ptr(),
+                0,
+            );
+
+            let mut actual_type: Atom = std::mem::zeroed();
+            let mut actual_format: i32 = std::mem::zeroed();
+            let mut nitems: u64 = std::mem::zeroed();
+            let mut bytes_after: u64 = std::mem::zeroed();
+            let mut data: *mut u8 = std::ptr::null_mut();
+
+            if XGetWindowProperty(
+                self.display,
+                window,
+                net_wm_name,
+                0,
+                !0,
+                0,
+                utf8,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut data,
+            ) == 0
+                && !data.is_null()
+            {
+                let win_title = std::ffi::CStr::from_ptr(data as *const i8)
+                    .to_string_lossy()
+                    .into_owned();
+                xlib::XFree(data as *mut _);
+                return Ok(win_title);
+            }
+        }
+
+        Err(GridWMError::Other(format!(
+            "failed to get name for window {}",
+            window
+        )))
+    }
+
+    fn get_class(&self, window: Window) -> Option<(String, String)> {
+        unsafe {
+            let mut hint: xlib::XClassHint = zeroed();
+            if xlib::XGetClassHint(self.display, window, &mut hint) == 0 {
+                return None;
+            }
+
+            let mut class = (String::new(), String::new());
+            if !hint.res_name.is_null() {
+                class.0 = std::ffi::CStr::from_ptr(hint.res_name)
+                    .to_string_lossy()
+                    .into_owned();
+                xlib::XFree(hint.res_name as *mut _);
+            }
+            if !hint.res_class.is_null() {
+                class.1 = std::ffi::CStr::from_ptr(hint.res_class)
+                    .to_string_lossy()
+                    .into_owned();
+                xlib::XFree(hint.res_class as *mut _);
+            }
+
+            // (instance, class)
+            Some(class)
+        }
+    }
+
+    // focused window if it is managed on the current desktop
+    fn focused_client(&self) -> Option<Window> {
+        self.focused
+            .filter(|w| self.get_desktop(self.current_desktop()).contains(w))
+    }
+
+    // focuses a window following the ICCCM input models
+    fn focus(&mut self, window: Window) {
+        let accepts_input = self.accepts_input(window);
+        let take_focus = has_protocol(self.display, window, "WM_TAKE_FOCUS");
+
+        // no input model, the window never gets focus
+        if !accepts_input && !take_focus {
+            return;
+        }
+
+        // passive and locally active windows get the focus set,
+        // locally and globally active ones are told to take it
+        if accepts_input {
+            unsafe {
+                xlib::XSetInputFocus(
+                    self.display,
+                    window,
+                    xlib::RevertToPointerRoot,
+                    xlib::CurrentTime,
+                );
+            }
+        }
+        if take_focus {
+            send_wm_take_focus(self.display, window);
+        }
+
+        self.focused = Some(window);
+        if let Some(monitor) = self.monitor_showing(self.desktop_of(window)) {
+            self.current_monitor = monitor;
+        }
+        self.focus_history.retain(|&w| w != window);
+        self.focus_history.insert(0, window);
+        self.update_borders();
+        self.trigger_redraw = true;
+    }
+
+    fn can_focus(&self, window: Window) -> bool {
+        self.accepts_input(window) || has_protocol(self.display, window, "WM_TAKE_FOCUS")
+    }
+
+    // input field of WM_HINTS, true if not set
+    fn accepts_input(&self, window: Window) -> bool {
+        unsafe {
+            let hints = xlib::XGetWMHints(self.display, window);
+            if hints.is_null() {
+                return true;
+            }
+            let input = (*hints).flags & xlib::InputHint == 0 || (*hints).input != 0;
+            xlib::XFree(hints as *mut _);
+            input
+        }
+    }
+
+    fn update_borders(&self) {
+        let visible: BTreeSet<Window> = self
+            .monitors
+            .iter()
+            .flat_map(|m| self.get_desktop(m.desktop))
+            .collect();
+        for &window in &visible {
+            let color = if self.focused == Some(window) {
+                self.border_colors.focused
+            } else if self.is_urgent(window) {
+                self.border_colors.urgent
+            } else if self.floating_windows.contains(&window) {
+                self.border_colors.floating
+            } else {
+                self.border_colors.unfocused
+            };
+            unsafe {
+                xlib::XSetWindowBorderWidth(self.display, window, self.border_width(window));
+                xlib::XSetWindowBorder(self.display, window, color);
+            }
+        }
+    }
+
+    // fullscreen windows, docks and notifications have no border
+    fn border_width(&self, window: Window) -> u32 {
+        let fullscreen = self.get_atom("_NET_WM_STATE_FULLSCREEN");
+        let borderless = matches!(
+            self.get_window_type(window).as_deref(),
+            Some("_NET_WM_WINDOW_TYPE_DOCK" | "_NET_WM_WINDOW_TYPE_NOTIFICATION")
+        );
+        if borderless || self.get_net_wm_state(window).contains(&fullscreen) {
+            0
+        } else {
+            self.config.window.border_width
+        }
+    }
+
+    fn is_urgent(&self, window: Window) -> bool {
+        unsafe {
+            let hints = xlib::XGetWMHints(self.display, window);
+            if hints.is_null() {
+                return false;
+            }
+            let urgent = (*hints).flags & xlib::XUrgencyHint != 0;
+            xlib::XFree(hints as *mut _);
+            urgent
+        }
+    }
+
+    // focuses another window on the current desktop when the focused one went away
+    fn focus_fallback(&mut self) {
+        let next = self
+            .recent_windows(Some(self.current_desktop()))
+            .into_iter()
+            .find(|&w| self.window_exists(w) && self.can_focus(w));
+
+        match next {
+            Some(window) => self.focus(window),
+            None => {
+                self.focused = None;
+                self.update_borders();
+                unsafe {
+                    xlib::XSetInputFocus(
+                        self.display,
+                        xlib::PointerRoot as Window,
+                        xlib::RevertToPointerRoot,
+                        xlib::CurrentTime,
+                    );
+                }
+            }
+        }
+    }
+
+    fn remove_window(&mut self, event: xlib::XEvent) {
+        let event: xlib::XUnmapEvent = From::from(event);
+
+        // unmapped by us, not by the client
+        if let Some(count) = self.ignore_unmaps.get_mut(&event.window) {
+            *count -= 1;
+            if *count == 0 {
+                self.ignore_unmaps.remove(&event.window);
+            }
+            return;
+        }
+
+        let index = self.desktop_of(event.window);
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&event.window);
+        }
+        self.floating_windows.remove(&event.window);
+        self.minimized.retain(|&w| w != event.window);
+        self.sticky.remove(&event.window);
+        self.focus_history.retain(|&w| w != event.window);
+        // hiding the window counts as closing it, e.g. for apps that close to the tray
+        self.pending_closes.remove(&event.window);
+        self.leave_tab_group(event.window);
+        self.set_wm_state(event.window, WITHDRAWN_STATE);
+        self.unswallow(event.window, index);
+
+        if self.focused == Some(event.window) {
+            self.focus_fallback();
+        }
+
+        if let Some(bar_win) = self.win_bar_windows.remove(&event.window) {
+            unsafe {
+                xlib::XDestroyWindow(self.display, bar_win);
+            }
+        }
+    }
+
+    fn destroy_window(&mut self, event: xlib::XEvent) {
+        let event: xlib::XDestroyWindowEvent = From::from(event);
+        let index = self.desktop_of(event.window);
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&event.window);
+        }
+        self.floating_windows.remove(&event.window);
+        self.scratchpad.remove(&event.window);
+        self.named_scratchpads.retain(|_, win| *win != event.window);
+        self.no_title_bar.remove(&event.window);
+        self.skip_focus.remove(&event.window);
+        self.minimized.retain(|&w| w != event.window);
+        self.ignore_unmaps.remove(&event.window);
+        self.ignore_maps.remove(&event.window);
+        self.sticky.remove(&event.window);
+        self.terminals.remove(&event.window);
+        self.float_geometry.remove(&event.window);
+        self.pending_closes.remove(&event.window);
+        self.focus_history.retain(|&w| w != event.window);
+        self.leave_tab_group(event.window);
+
+        if self.focused == Some(event.window) {
+            self.focus_fallback();
+        }
+        self.swallowed
+            .retain(|_, terminal| *terminal != event.window);
+        self.unswallow(event.window, index);
+
+        if let Some(bar_win) = self.win_bar_windows.remove(&event.window) {
+            unsafe {
+                xlib::XDestroyWindow(self.display, bar_win);
+            }
+        }
+    }
+
+    // index of the desktop a window is on, current desktop if none
+    fn desktop_of(&self, window: Window) -> usize {
+        self.desktops
+            .iter()
+            .position(|d| d.contains(&window))
+            .unwrap_or(self.current_desktop())
+    }
+
+    fn window_exists(&self, window: Window) -> bool {
+        let mut attrs: XWindowAttributes = unsafe { zeroed() };
+        unsafe { xlib::XGetWindowAttributes(self.display, window, &mut attrs) != 0 }
+    }
+
+    fn move_window(&mut self, window: Window, x: i32, y: i32) {
         unsafe { xlib::XMoveWindow(self.display, window, x, y) };
         self.trigger_redraw = true;
     }
 
-    fn resize_window(&mut self, window: Window, width: u32, height: u32) {
-        unsafe { xlib::XResizeWindow(self.display, window, width, height) };
-        self.trigger_redraw = true;
+    fn resize_window(&mut self, window: Window, width: u32, height: u32) {
+        unsafe { xlib::XResizeWindow(self.display, window, width, height) };
+        self.trigger_redraw = true;
+    }
+
+    fn move_resize_window(&mut self, window: Window, x: i32, y: i32, width: u32, height: u32) {
+        unsafe { xlib::XMoveResizeWindow(self.display, window, x, y, width, height) };
+        self.trigger_redraw = true;
+    }
+
+    fn get_window_attributes(&self, window: Window) -> XWindowAttributes {
+        let mut attrs: XWindowAttributes = unsafe { zeroed() };
+        unsafe {
+            xlib::XGetWindowAttributes(self.display, window, &mut attrs);
+        }
+        attrs
+    }
+
+    fn handle_key(&mut self, event: xlib::XEvent) {
+        let event: xlib::XKeyPressedEvent = From::from(event);
+
+        if self.switcher.is_some()
+            && unsafe { xlib::XKeycodeToKeysym(self.display, event.keycode as u8, 0) }
+                == x11::keysym::XK_Escape as u64
+        {
+            self.finish_switch(false);
+            return;
+        }
+
+        let mask = event.state & RELEVANT_MODIFIERS;
+        match self.key_table.get(&(mask, event.keycode)).cloned() {
+            Some(KeyAction::Gridwm(action)) => self.run_action(&action, None),
+            Some(KeyAction::Exec(command)) => {
+                if let Err(e) = spawn(&command) {
+                    error!("failed to run keybinding '{}': {}", command, e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // (re)grabs the keybinds with the current keycodes, e.g. after the layout changed
+    fn grab_keys(&mut self) {
+        let root = unsafe { XDefaultRootWindow(self.display) };
+        self.key_table = key_table(self.display, &self.keybinds);
+
+        unsafe {
+            xlib::XUngrabKey(self.display, xlib::AnyKey, xlib::AnyModifier, root);
+            for &(mask, keycode) in self.key_table.keys() {
+                for &extra_mod in &EXTRA_MODS {
+                    xlib::XGrabKey(
+                        self.display,
+                        keycode as i32,
+                        mask | extra_mod,
+                        root,
+                        0,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                    );
+                }
+            }
+        }
+    }
+
+    // runs a gridwm action on the target window, or the focused one if there is none
+    fn run_action(&mut self, action: &str, target: Option<Window>) {
+        let mut args = action.split_whitespace();
+        let client = target.or_else(|| self.focused_client());
+
+        match args.next().unwrap_or_default() {
+            "close" => {
+                if let Some(win) = client {
+                    self.close_window(win);
+                }
+            }
+            "close_under_pointer" => {
+                if let Some(win) = self.window_under_pointer() {
+                    let win = self.get_toplevel(win);
+                    self.close_window(win);
+                }
+            }
+            "force_close" => {
+                if let Some(win) = client {
+                    self.kill_window(win);
+                }
+            }
+            "desktop_right" => {
+                self.change_desktop(self.current_desktop() + 1);
+            }
+            "desktop_left" => {
+                self.change_desktop(if self.current_desktop() > 0 {
+                    self.current_desktop() - 1
+                } else {
+                    0
+                });
+            }
+            "unfloat" => {
+                // unfloats all windows on the current desktop
+                for win in self.get_desktop(self.current_desktop()) {
+                    if self.floating_windows.contains(&win) && !self.sticky.contains(&win) {
+                        self.unfloat_window(win);
+                    }
+                }
+                self.layout();
+            }
+            "toggle_float" => {
+                if let Some(win) = client {
+                    if self.floating_windows.contains(&win) {
+                        self.unfloat_window(win);
+                        self.layout();
+                    } else {
+                        self.float_window(win);
+                    }
+                }
+            }
+            "scratchpad" => {
+                if let Some(win) = client {
+                    self.scratchpad.insert(win);
+                    self.hide_scratchpad(win);
+                }
+            }
+            "scratchpad_toggle" => {
+                self.toggle_scratchpad(args.next());
+            }
+            "minimize" => {
+                if let Some(win) = client {
+                    self.minimize(win);
+                }
+            }
+            "restore_last" => {
+                if let Some(&win) = self.minimized.last() {
+                    self.restore(win);
+                }
+            }
+            "restore" => {
+                self.open_restore_picker();
+            }
+            "move_left" => self.move_by(client, -1, 0),
+            "move_right" => self.move_by(client, 1, 0),
+            "move_up" => self.move_by(client, 0, -1),
+            "move_down" => self.move_by(client, 0, 1),
+            "resize_grow_width" => self.resize_by(client, 1, 0),
+            "resize_shrink_width" => self.resize_by(client, -1, 0),
+            "resize_grow_height" => self.resize_by(client, 0, 1),
+            "resize_shrink_height" => self.resize_by(client, 0, -1),
+            "center" => {
+                if let Some(win) = client {
+                    self.float_window(win);
+                    self.center_window(win, self.screen_area());
+                }
+            }
+            "center_on_monitor" => {
+                if let Some(win) = client {
+                    self.float_window(win);
+                    self.center_window(win, self.work_area(self.monitor_of(win)));
+                }
+            }
+            "focus_monitor_left" => self.focus_monitor(-1),
+            "focus_monitor_right" => self.focus_monitor(1),
+            "move_to_monitor" => {
+                if let Some(win) = client {
+                    self.move_to_monitor(win, args.next());
+                }
+            }
+            "tab_join" => {
+                if let Some(win) = client {
+                    self.join_tab_group(win);
+                }
+            }
+            "tab_leave" => {
+                if let Some(win) = client
+                    && self.leave_tab_group(win)
+                {
+                    self.map_window(win);
+                    self.layout();
+                    self.focus(win);
+                }
+            }
+            "tab_next" => {
+                if let Some(win) = client {
+                    self.cycle_tab(win, 1);
+                }
+            }
+            "tab_prev" => {
+                if let Some(win) = client {
+                    self.cycle_tab(win, -1);
+                }
+            }
+            "switch_window" => self.switch_window(args.next() == Some("all")),
+            "restart" => self.restart(),
+            "session_save" => self.session_save(args.next()),
+            "session_restore" => self.session_restore(args.next()),
+            "toggle_sticky" => {
+                if let Some(win) = client {
+                    self.set_sticky(win, !self.sticky.contains(&win));
+                }
+            }
+            other => {
+                warn!("unknown action \"{}\"", other);
+            }
+        }
+    }
+
+    fn tab_group_of(&self, window: Window) -> Option<usize> {
+        self.tab_groups
+            .iter()
+            .position(|g| g.windows.contains(&window))
+    }
+
+    fn is_hidden_tab(&self, window: Window) -> bool {
+        self.tab_groups
+            .iter()
+            .any(|g| g.windows.contains(&window) && g.windows[g.active] != window)
+    }
+
+    // window becomes the active tab in the group of the previously focused window
+    fn join_tab_group(&mut self, window: Window) {
+        let Some(target) = self
+            .recent_windows(Some(self.desktop_of(window)))
+            .into_iter()
+            .find(|&w| w != window && !self.floating_windows.contains(&w) && self.is_tileable(w))
+        else {
+            return;
+        };
+
+        self.leave_tab_group(window);
+        if self.floating_windows.contains(&window) {
+            self.unfloat_window(window);
+        }
+
+        let index = match self.tab_group_of(target) {
+            Some(index) => index,
+            None => {
+                self.tab_groups.push(TabGroup {
+                    windows: vec![target],
+                    active: 0,
+                });
+                self.tab_groups.len() - 1
+            }
+        };
+        let group = &mut self.tab_groups[index];
+        let previous = group.windows[group.active];
+        group.windows.push(window);
+        group.active = group.windows.len() - 1;
+
+        self.hide_tab(previous);
+        self.focus(window);
+        self.layout();
+        self.trigger_redraw = true;
+    }
+
+    // returns false if the window wasn't in a group
+    fn leave_tab_group(&mut self, window: Window) -> bool {
+        let Some(index) = self.tab_group_of(window) else {
+            return false;
+        };
+        let group = &mut self.tab_groups[index];
+        let pos = group.windows.iter().position(|&w| w == window).unwrap_or(0);
+        let was_active = pos == group.active;
+        group.windows.remove(pos);
+
+        if group.windows.len() == 1 {
+            // a single tab is a normal window again, without the tab strip
+            let last = group.windows[0];
+            self.tab_groups.remove(index);
+            if was_active && self.is_shown(self.desktop_of(last)) {
+                self.map_window(last);
+            }
+            if !self.config.window.window_bars
+                && let Some(bar_win) = self.win_bar_windows.remove(&last)
+            {
+                unsafe { xlib::XDestroyWindow(self.display, bar_win) };
+            }
+            self.layout();
+        } else if group.windows.is_empty() {
+            self.tab_groups.remove(index);
+        } else {
+            if pos < group.active || group.active == group.windows.len() {
+                group.active -= 1;
+            }
+            // the next tab takes the place of the active one
+            let next = group.windows[group.active];
+            if was_active && self.is_shown(self.desktop_of(next)) {
+                self.map_window(next);
+            }
+        }
+
+        if let Some(bar_win) = self.win_bar_windows.remove(&window) {
+            unsafe { xlib::XDestroyWindow(self.display, bar_win) };
+        }
+        self.trigger_redraw = true;
+        true
+    }
+
+    fn select_tab(&mut self, window: Window) {
+        let Some(index) = self.tab_group_of(window) else {
+            return;
+        };
+        let group = &mut self.tab_groups[index];
+        let previous = group.windows[group.active];
+        if previous == window {
+            return;
+        }
+        group.active = group.windows.iter().position(|&w| w == window).unwrap_or(0);
+
+        let shown = self.is_shown(self.desktop_of(window));
+        if shown {
+            self.map_window(window);
+        }
+        self.hide_tab(previous);
+        self.layout();
+        if shown {
+            self.focus(window);
+        }
+        self.trigger_redraw = true;
+    }
+
+    fn cycle_tab(&mut self, window: Window, step: isize) {
+        let Some(index) = self.tab_group_of(window) else {
+            return;
+        };
+        let group = &self.tab_groups[index];
+        let next = (group.active as isize + step).rem_euclid(group.windows.len() as isize);
+        self.select_tab(group.windows[next as usize]);
+    }
+
+    fn hide_tab(&mut self, window: Window) {
+        if let Some(bar_win) = self.win_bar_windows.remove(&window) {
+            unsafe { xlib::XDestroyWindow(self.display, bar_win) };
+        }
+        self.unmap_window(window);
+        if self.focused == Some(window) {
+            self.focused = None;
+        }
+    }
+
+    // windows of a desktop, or all, most recently focused first
+    fn recent_windows(&self, desktop: Option<usize>) -> Vec<Window> {
+        let windows: Vec<Window> = match desktop {
+            Some(index) => self.get_desktop(index).into_iter().collect(),
+            None => self.desktops.iter().flatten().copied().collect(),
+        };
+        let mut recent: Vec<Window> = self
+            .focus_history
+            .iter()
+            .copied()
+            .filter(|w| windows.contains(w))
+            .collect();
+        // never focused ones come last, newest first
+        let rest: Vec<Window> = windows
+            .iter()
+            .rev()
+            .copied()
+            .filter(|w| !recent.contains(w))
+            .collect();
+        recent.extend(rest);
+        recent.retain(|&w| !self.minimized.contains(&w) && !self.is_hidden_tab(w));
+        recent
+    }
+
+    fn switch_window(&mut self, all: bool) {
+        if let Some(switcher) = &mut self.switcher {
+            switcher.index = (switcher.index + 1) % switcher.windows.len();
+            self.draw_switcher();
+            return;
+        }
+
+        let windows = self.recent_windows((!all).then_some(self.current_desktop()));
+        if windows.len() < 2 {
+            return;
+        }
+
+        // without a held modifier there is no release to wait for
+        let (_, _, _, mask) = self.query_pointer();
+        let root = unsafe { XDefaultRootWindow(self.display) };
+        if mask & RELEVANT_MODIFIERS == 0
+            || unsafe {
+                xlib::XGrabKeyboard(
+                    self.display,
+                    root,
+                    0,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                    xlib::CurrentTime,
+                )
+            } != xlib::GrabSuccess
+        {
+            self.switch_to(windows[1]);
+            return;
+        }
+
+        let area = self.monitors[self.current_monitor].area;
+        let line_height = self.config.window.window_bar_height.max(1);
+        let width = (area.w as u32 / 3).max(1);
+        let height = line_height * windows.len() as u32;
+        let overlay = unsafe {
+            let overlay = xlib::XCreateSimpleWindow(
+                self.display,
+                root,
+                area.x + (area.w - width as i32) / 2,
+                area.y + (area.h - height as i32) / 2,
+                width,
+                height,
+                0,
+                0,
+                0,
+            );
+            let mut attrs: xlib::XSetWindowAttributes = zeroed();
+            attrs.override_redirect = 1;
+            xlib::XChangeWindowAttributes(
+                self.display,
+                overlay,
+                xlib::CWOverrideRedirect,
+                &mut attrs,
+            );
+            xlib::XSelectInput(self.display, overlay, xlib::ExposureMask);
+            xlib::XMapRaised(self.display, overlay);
+            overlay
+        };
+
+        self.switcher = Some(Switcher {
+            windows,
+            index: 1,
+            overlay,
+        });
+        self.draw_switcher();
+    }
+
+    fn draw_switcher(&self) {
+        let Some(switcher) = &self.switcher else {
+            return;
+        };
+        let line_height = self.config.window.window_bar_height.max(1);
+        let width = self.get_window_attributes(switcher.overlay).width.max(1) as u32;
+
+        for (i, &window) in switcher.windows.iter().enumerate() {
+            // the selected entry has its colors swapped
+            let (background, text) = if i == switcher.index {
+                (self.win_bar_gc, self.win_bar_background_gc)
+            } else {
+                (self.win_bar_background_gc, self.win_bar_gc)
+            };
+            let y = (line_height * i as u32) as i32;
+            let name = CString::new(self.get_name(window).unwrap_or_default()).unwrap_or_default();
+            unsafe {
+                xlib::XFillRectangle(
+                    self.display,
+                    switcher.overlay,
+                    background,
+                    0,
+                    y,
+                    width,
+                    line_height,
+                );
+                xlib::XDrawString(
+                    self.display,
+                    switcher.overlay,
+                    text,
+                    5,
+                    y + line_height as i32 * 3 / 4,
+                    name.as_ptr(),
+                    name.to_bytes().len() as i32,
+                );
+            }
+        }
+    }
+
+    // letting go of the modifier picks the selected window
+    fn handle_key_release(&mut self, event: xlib::XKeyEvent) {
+        if self.switcher.is_none() {
+            return;
+        }
+        let keysym = unsafe { xlib::XKeycodeToKeysym(self.display, event.keycode as u8, 0) } as u32;
+        if (x11::keysym::XK_Shift_L..=x11::keysym::XK_Hyper_R).contains(&keysym) {
+            self.finish_switch(true);
+        }
     }
 
-    fn move_resize_window(&mut self, window: Window, x: i32, y: i32, width: u32, height: u32) {
-        unsafe { xlib::XMoveResizeWindow(self.display, window, x, y, width, height) };
+    fn finish_switch(&mut self, commit: bool) {
+        let Some(switcher) = self.switcher.take() else {
+            return;
+        };
+        unsafe {
+            xlib::XUngrabKeyboard(self.display, xlib::CurrentTime);
+            xlib::XDestroyWindow(self.display, switcher.overlay);
+        }
+        if commit {
+            self.switch_to(switcher.windows[switcher.index]);
+        }
+    }
+
+    fn switch_to(&mut self, window: Window) {
+        if !self.window_exists(window) {
+            return;
+        }
+        let index = self.desktop_of(window);
+        if !self.is_shown(index) {
+            self.change_desktop(index);
+            self.layout();
+        }
+        // focusing also makes its monitor the current one
+        self.focus(window);
+        unsafe { xlib::XRaiseWindow(self.display, window) };
         self.trigger_redraw = true;
     }
 
-    fn get_window_attributes(&self, window: Window) -> XWindowAttributes {
-        let mut attrs: XWindowAttributes = unsafe { zeroed() };
+    fn window_under_pointer(&self) -> Option<Window> {
+        let (child, _, _, _) = self.query_pointer();
+        if child == 0 { None } else { Some(child) }
+    }
+
+    // child of root under the pointer, its position and the held modifiers
+    fn query_pointer(&self) -> (Window, i32, i32, u32) {
         unsafe {
-            xlib::XGetWindowAttributes(self.display, window, &mut attrs);
+            let root = XDefaultRootWindow(self.display);
+            let mut root_return: Window = 0;
+            let mut child: Window = 0;
+            let mut root_x = 0;
+            let mut root_y = 0;
+            let mut win_x = 0;
+            let mut win_y = 0;
+            let mut mask = 0;
+            xlib::XQueryPointer(
+                self.display,
+                root,
+                &mut root_return,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            );
+            (child, root_x, root_y, mask)
         }
-        attrs
     }
 
-    fn handle_key(&mut self, event: xlib::XEvent) {
-        let event: xlib::XKeyPressedEvent = From::from(event);
+    fn toggle_scratchpad(&mut self, name: Option<&str>) {
+        let Some(name) = name else {
+            // unnamed scratchpad windows are all shown or hidden together
+            let named: HashSet<Window> = self.named_scratchpads.values().copied().collect();
+            let windows: Vec<Window> = self
+                .scratchpad
+                .iter()
+                .copied()
+                .filter(|w| !named.contains(w))
+                .collect();
+            let desktop = self.get_desktop(self.current_desktop());
+            let shown: Vec<Window> = windows
+                .iter()
+                .copied()
+                .filter(|w| desktop.contains(w))
+                .collect();
 
-        // check keybindings and execute
-        let gridwm_binds = self.config.keybinds.gridwm.clone();
-        for bind in &gridwm_binds {
-            if bind.len() != 2 {
-                error!("failed to parse keybind {:?}: invalid length.", bind);
-                continue;
+            if shown.is_empty() {
+                for window in windows {
+                    self.show_scratchpad(window);
+                }
+            } else {
+                for window in shown {
+                    self.hide_scratchpad(window);
+                }
             }
+            return;
+        };
 
-            let (mask, keycode): (u32, i32) = match parse_keybind(self.display, bind[0].clone()) {
-                Some((a, Some(b))) => (a, b),
-                _ => {
-                    warn!("failed to parse keybind: {:?}", bind);
-                    continue;
-                }
-            };
+        let Some(entry) = self
+            .config
+            .scratchpads
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+        else {
+            warn!("no scratchpad \"{}\" configured", name);
+            return;
+        };
 
-            let relevant_modifiers: u32 =
-                xlib::ControlMask | xlib::ShiftMask | xlib::Mod1Mask | xlib::Mod4Mask;
-            let event_mask = event.state & relevant_modifiers;
+        // use the window claimed before or look for a matching one
+        let window = self
+            .named_scratchpads
+            .get(name)
+            .copied()
+            .filter(|&w| self.window_exists(w))
+            .or_else(|| {
+                self.desktops
+                    .iter()
+                    .flatten()
+                    .chain(self.scratchpad.iter())
+                    .copied()
+                    .find(|&w| self.scratchpad_matches(name, w))
+            });
 
-            if event_mask == mask && event.keycode as i32 == keycode {
-                match bind[1].as_str() {
-                    "close" => {
-                        if event.subwindow != unsafe { XDefaultRootWindow(self.display) }
-                            && event.subwindow != 0
-                        {
-                            send_wm_delete_window(self.display, event.subwindow);
-                            unsafe {
-                                XUnmapWindow(self.display, event.subwindow);
-                            }
-                        }
-                    }
-                    "desktop_right" => {
-                        self.change_desktop(self.current_desktop + 1);
-                    }
-                    "desktop_left" => {
-                        self.change_desktop(if self.current_desktop > 0 {
-                            self.current_desktop - 1
-                        } else {
-                            0
-                        });
+        match window {
+            Some(window) => {
+                self.named_scratchpads.insert(name.to_string(), window);
+                self.scratchpad.insert(window);
+                if self.get_desktop(self.current_desktop()).contains(&window) {
+                    self.hide_scratchpad(window);
+                } else {
+                    self.show_scratchpad(window);
+                }
+            }
+            None => {
+                // still starting, unless it never showed up
+                if self
+                    .pending_scratchpads
+                    .get(name)
+                    .is_some_and(|started| started.elapsed() < LAUNCH_TIMEOUT)
+                {
+                    return;
+                }
+                match spawn(&entry.command) {
+                    Ok(_) => {
+                        self.pending_scratchpads
+                            .insert(name.to_string(), Instant::now());
                     }
-                    "unfloat" => {
-                        // unfloats all windows
-                        self.floating_windows.clear();
-                        self.layout();
+                    Err(e) => {
+                        self.pending_scratchpads.remove(name);
+                        error!("failed to run command for scratchpad \"{}\": {}", name, e);
                     }
-                    _ => {}
                 }
             }
         }
+    }
 
-        // check keybindings for commands and execute
-        let exec_binds = self.config.keybinds.exec.clone();
-        for bind in &exec_binds {
-            if bind.len() != 2 {
-                error!("failed to parse keybind {:?}: invalid length.", bind);
-                continue;
+    fn scratchpad_matches(&self, name: &str, window: Window) -> bool {
+        let Some(entry) = self.config.scratchpads.iter().find(|s| s.name == name) else {
+            return false;
+        };
+        if entry.class.is_empty() {
+            return false;
+        }
+        match self.get_class(window) {
+            Some((instance, class)) => instance == entry.class || class == entry.class,
+            None => false,
+        }
+    }
+
+    fn show_scratchpad(&mut self, window: Window) {
+        if !self.window_exists(window) {
+            self.scratchpad.remove(&window);
+            return;
+        }
+
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&window);
+        }
+        let mut desktop = self.get_desktop(self.current_desktop());
+        desktop.insert(window);
+        self.set_desktop(self.current_desktop(), desktop);
+        self.floating_windows.insert(window);
+
+        // named scratchpads have a configured size, others keep theirs
+        let entry = self
+            .named_scratchpads
+            .iter()
+            .find(|(_, win)| **win == window)
+            .and_then(|(name, _)| self.config.scratchpads.iter().find(|s| s.name == *name));
+        let area = self.monitors[self.current_monitor].area;
+        let (width, height) = match entry {
+            Some(entry) => (
+                (area.w as f32 * entry.width) as u32,
+                (area.h as f32 * entry.height) as u32,
+            ),
+            None => {
+                let attrs = self.get_window_attributes(window);
+                (attrs.width as u32, attrs.height as u32)
             }
+        };
 
-            let (mask, keycode): (u32, i32) = match parse_keybind(self.display, bind[0].clone()) {
-                Some((a, Some(b))) => (a, b),
-                _ => {
-                    warn!("failed to parse keybind: {:?}", bind);
-                    continue;
-                }
-            };
+        self.move_resize_window(
+            window,
+            area.x + (area.w - width as i32) / 2,
+            area.y + (area.h - height as i32) / 2,
+            width,
+            height,
+        );
 
-            let relevant_modifiers: u32 =
-                xlib::ControlMask | xlib::ShiftMask | xlib::Mod1Mask | xlib::Mod4Mask;
-            let event_mask = event.state & relevant_modifiers;
+        self.map_window(window);
+        unsafe { xlib::XRaiseWindow(self.display, window) };
+        self.focus(window);
+        self.layout();
+    }
 
-            if event_mask == mask && event.keycode as i32 == keycode {
-                match shell_words::split(bind[1].as_str()) {
-                    Ok(parts) => {
-                        let program = &parts[0];
-                        let args = &parts[1..];
-                        let _ = Command::new(program).args(args).spawn();
-                    }
-                    Err(e) => {
-                        error!("Failed to parse keybinding '{}': {}", bind[1], e);
-                    }
-                }
+    fn hide_scratchpad(&mut self, window: Window) {
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(&window);
+        }
+        self.floating_windows.remove(&window);
+
+        unsafe {
+            if let Some(bar_win) = self.win_bar_windows.remove(&window) {
+                xlib::XDestroyWindow(self.display, bar_win);
+            }
+        }
+        self.unmap_window(window);
+        self.layout();
+        self.trigger_redraw = true;
+
+        if self.focused == Some(window) {
+            self.focus_fallback();
+        }
+    }
+
+    // makes a window float where it was last floating, if it was before
+    fn float_window(&mut self, win: Window) {
+        self.leave_tab_group(win);
+        if self.floating_windows.insert(win) {
+            if let Some(geometry) = self.float_geometry.get(&win).copied() {
+                let geometry = self.clamp_to_area(win, geometry);
+                self.move_resize_window(
+                    win,
+                    geometry.x,
+                    geometry.y,
+                    geometry.w as u32,
+                    geometry.h as u32,
+                );
             }
+            self.layout();
+        }
+        unsafe { xlib::XRaiseWindow(self.display, win) };
+    }
+
+    // tiles a window again, remembering where it was floating
+    fn unfloat_window(&mut self, win: Window) {
+        if self.floating_windows.remove(&win) {
+            let attrs = self.get_window_attributes(win);
+            self.float_geometry.insert(
+                win,
+                WindowInfo {
+                    x: attrs.x,
+                    y: attrs.y,
+                    w: attrs.width,
+                    h: attrs.height,
+                },
+            );
         }
     }
 
+    fn move_by(&mut self, window: Option<Window>, dx: i32, dy: i32) {
+        let Some(win) = window else {
+            return;
+        };
+        self.float_window(win);
+        let step = self.config.window.scale_steps as i32;
+        let attrs = self.get_window_attributes(win);
+        self.move_window(win, attrs.x + dx * step, attrs.y + dy * step);
+    }
+
+    fn resize_by(&mut self, window: Option<Window>, dw: i32, dh: i32) {
+        let Some(win) = window else {
+            return;
+        };
+        self.float_window(win);
+        let step = self.config.window.scale_steps as i32;
+        let attrs = self.get_window_attributes(win);
+        self.resize_window(
+            win,
+            (attrs.width + dw * step).max(100) as u32,
+            (attrs.height + dh * step).max(100) as u32,
+        );
+    }
+
+    fn center_window(&mut self, window: Window, area: WindowInfo) {
+        let attrs = self.get_window_attributes(window);
+        let top_offset = self.title_bar_height(window);
+        self.move_window(
+            window,
+            area.x + (area.w - attrs.width) / 2,
+            area.y + top_offset + (area.h - top_offset - attrs.height) / 2,
+        );
+    }
+
     fn get_toplevel(&self, mut window: Window) -> Window {
         unsafe {
             loop {
@@ -840,43 +3053,155 @@ impl GridWM {
         }
     }
 
+    fn click_target(&self, event: &xlib::XButtonEvent) -> ClickTarget {
+        let root = unsafe { XDefaultRootWindow(self.display) };
+        let window = if event.subwindow != 0 {
+            event.subwindow
+        } else {
+            event.window
+        };
+
+        if let Some((&parent_win, _)) = self
+            .win_bar_windows
+            .iter()
+            .find(|(_, bar_win)| **bar_win == window)
+        {
+            ClickTarget::TitleBar(parent_win)
+        } else if window != root {
+            ClickTarget::Window(self.get_toplevel(window))
+        } else if self.config.bar.enable
+            && self
+                .monitor_at(event.x_root, event.y_root)
+                .is_some_and(|m| {
+                    event.y_root < self.monitors[m].area.y + self.config.bar.height as i32
+                })
+        {
+            ClickTarget::Bar
+        } else {
+            ClickTarget::Root
+        }
+    }
+
+    // runs the mousebind for a button event, returns false if there is none
+    fn handle_mousebind(&mut self, event: xlib::XButtonEvent, release: bool) -> bool {
+        let (target, window) = match self.click_target(&event) {
+            ClickTarget::Window(win) => (MouseTarget::Window, Some(win)),
+            ClickTarget::TitleBar(win) => (MouseTarget::TitleBar, Some(win)),
+            ClickTarget::Root => (MouseTarget::Root, None),
+            ClickTarget::Bar => (MouseTarget::Bar, None),
+        };
+        let mask = event.state & RELEVANT_MODIFIERS;
+        let Some(action) = self
+            .mouse_table
+            .get(&(target, mask, event.button, release))
+            .cloned()
+        else {
+            return false;
+        };
+
+        match (action.as_str(), window) {
+            ("move", Some(win)) => self.handle_drag_start(win, event),
+            ("resize", Some(win)) => self.handle_resize_start(win, event),
+            ("scale_up", Some(win)) => self.scale_up(win),
+            ("scale_down", Some(win)) => self.scale_down(win),
+            ("move" | "resize" | "scale_up" | "scale_down", None) => {}
+            (action, window) => self.run_action(action, window),
+        }
+
+        // pointer may still be frozen by the click to focus grab
+        unsafe {
+            xlib::XAllowEvents(self.display, xlib::AsyncPointer, xlib::CurrentTime);
+        }
+        true
+    }
+
     fn handle_button(&mut self, event: xlib::XEvent) {
         let event: XButtonPressedEvent = From::from(event);
 
-        // get toplevel window if child was clicked
-        let clicked_win = if event.subwindow != 0 {
-            self.get_toplevel(event.subwindow)
-        } else {
-            event.subwindow
+        // clicking a tab selects it
+        if event.button == xlib::Button1
+            && let ClickTarget::TitleBar(parent) = self.click_target(&event)
+            && let Some(index) = self.tab_group_of(parent)
+            && let Some(&bar_win) = self.win_bar_windows.get(&parent)
+        {
+            let attrs = self.get_window_attributes(bar_win);
+            let count = self.tab_groups[index].windows.len() as i32;
+            let tab = ((event.x_root - attrs.x) * count / attrs.width.max(1)).clamp(0, count - 1);
+            self.select_tab(self.tab_groups[index].windows[tab as usize]);
+            unsafe { xlib::XAllowEvents(self.display, xlib::AsyncPointer, xlib::CurrentTime) };
+            return;
+        }
+
+        // get toplevel window if child was clicked, title bars stand for their window
+        let clicked_win = match self.click_target(&event) {
+            ClickTarget::Window(win) | ClickTarget::TitleBar(win) => win,
+            ClickTarget::Root | ClickTarget::Bar => 0,
         };
 
         if clicked_win != 0 {
             unsafe {
-                xlib::XSetInputFocus(
-                    self.display,
-                    clicked_win,
-                    xlib::RevertToPointerRoot,
-                    xlib::CurrentTime,
-                );
+                self.focus(clicked_win);
                 xlib::XRaiseWindow(self.display, clicked_win);
                 if let Some(&bar_win) = self.win_bar_windows.get(&clicked_win) {
                     xlib::XRaiseWindow(self.display, bar_win);
                 }
             }
             self.trigger_redraw = true;
-        }
-        unsafe {
-            xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime);
+
+            unsafe {
+                xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime);
+            }
+        } else {
+            // clicking an empty monitor makes it the current one
+            if let Some(monitor) = self.monitor_at(event.x_root, event.y_root)
+                && monitor != self.current_monitor
+            {
+                self.current_monitor = monitor;
+                self.focus_fallback();
+            }
+            // replaying a click on the root window would deliver it twice
+            unsafe {
+                xlib::XAllowEvents(self.display, xlib::AsyncPointer, xlib::CurrentTime);
+            }
         }
     }
 
     fn change_desktop(&mut self, index: usize) {
-        if index == self.current_desktop {
+        let current = self.current_desktop();
+        if index == current {
+            return;
+        }
+        let mut old_desktop = self.get_desktop(current);
+        let mut new_desktop = self.get_desktop(index);
+
+        // sticky windows move along to the new desktop
+        let sticky: Vec<Window> = old_desktop
+            .iter()
+            .copied()
+            .filter(|w| self.sticky.contains(w))
+            .collect();
+        for window in &sticky {
+            old_desktop.remove(window);
+            new_desktop.insert(*window);
+        }
+        self.set_desktop(current, old_desktop.clone());
+        self.set_desktop(index, new_desktop);
+
+        // a desktop shown on another monitor swaps places with the current one
+        if let Some(other) = self.monitor_showing(index) {
+            self.monitors[other].desktop = current;
+            self.monitors[self.current_monitor].desktop = index;
+            self.fit_floating(other);
+            self.fit_floating(self.current_monitor);
+            self.layout();
+            self.trigger_redraw = true;
+            if self.focused_client().is_none() {
+                self.focus_fallback();
+            }
             return;
         }
-        unsafe {
-            let old_desktop = self.get_desktop(self.current_desktop);
 
+        unsafe {
             for window in &old_desktop {
                 if let Some(bar_win) = self.win_bar_windows.remove(window) {
                     xlib::XDestroyWindow(self.display, bar_win);
@@ -884,22 +3209,189 @@ impl GridWM {
             }
 
             for window in old_desktop {
-                xlib::XUnmapWindow(self.display, window);
+                if self.minimized.contains(&window) {
+                    continue;
+                }
+                self.unmap_window(window);
                 xlib::XUnmapSubwindows(self.display, window);
             }
 
             for window in self.get_desktop(index) {
-                xlib::XMapWindow(self.display, window);
+                if self.minimized.contains(&window)
+                    || sticky.contains(&window)
+                    || self.is_hidden_tab(window)
+                {
+                    continue;
+                }
+                self.map_window(window);
                 xlib::XMapSubwindows(self.display, window);
             }
+        }
+        self.monitors[self.current_monitor].desktop = index;
+        self.fit_floating(self.current_monitor);
+
+        if self.focused_client().is_none() {
+            self.focus_fallback();
+        }
+    }
+
+    // re-reads the monitors after outputs were added, removed or changed
+    fn update_monitors(&mut self) {
+        let areas = match get_monitors(self.display) {
+            Ok(areas) => areas,
+            Err(e) => {
+                error!("failed to get monitors: {}", e);
+                return;
+            }
+        };
+        self.refresh_rate = get_refresh_rate(self.display);
+        let old_areas: Vec<WindowInfo> = self.monitors.iter().map(|m| m.area).collect();
+        if areas == old_areas {
+            return;
+        }
+        info!("monitors changed: {:?}", areas);
+
+        // monitors that are still there keep their desktops, new ones get unused desktops
+        let matched = match_monitors(&old_areas, &areas);
+        let old = std::mem::take(&mut self.monitors);
+        for (area, from) in areas.into_iter().zip(&matched) {
+            let desktop = match from {
+                Some(from) => old[*from].desktop,
+                None => (0..)
+                    .find(|d| {
+                        !old.iter().any(|m| m.desktop == *d)
+                            && !self.monitors.iter().any(|m| m.desktop == *d)
+                    })
+                    .unwrap_or(0),
+            };
+            self.monitors.push(Monitor { area, desktop });
+        }
+        self.current_monitor = matched
+            .iter()
+            .position(|&from| from == Some(self.current_monitor))
+            .unwrap_or(0);
+
+        // windows of vanished monitors go to the current one
+        let target = self.current_desktop();
+        for (i, monitor) in old.iter().enumerate() {
+            if matched.contains(&Some(i)) {
+                continue;
+            }
+            for window in self.get_desktop(monitor.desktop) {
+                self.send_to_desktop(window, target);
+            }
+        }
+
+        // desktops shown by added monitors were hidden until now
+        for monitor in (0..self.monitors.len()).filter(|&m| matched[m].is_none()) {
+            for window in self.get_desktop(self.monitors[monitor].desktop) {
+                if self.minimized.contains(&window) || self.is_hidden_tab(window) {
+                    continue;
+                }
+                self.map_window(window);
+                unsafe { xlib::XMapSubwindows(self.display, window) };
+            }
+        }
+
+        for monitor in 0..self.monitors.len() {
+            self.fit_floating(monitor);
+        }
+        unsafe { XClearWindow(self.display, XDefaultRootWindow(self.display)) };
+        self.layout();
+        self.trigger_redraw = true;
+    }
+
+    // brings floating windows of the desktop a monitor shows onto that monitor
+    fn fit_floating(&mut self, monitor: usize) {
+        let area = self.monitors[monitor].area;
+        for window in self.get_desktop(self.monitors[monitor].desktop) {
+            if !self.floating_windows.contains(&window) || self.sticky.contains(&window) {
+                continue;
+            }
+            let attrs = self.get_window_attributes(window);
+            let from = self.monitor_at(attrs.x + attrs.width / 2, attrs.y + attrs.height / 2);
+            if from == Some(monitor) {
+                continue;
+            }
+            // keep the position relative to the monitor it was on
+            let (dx, dy) = match from {
+                Some(from) => {
+                    let from = self.monitors[from].area;
+                    (area.x - from.x, area.y - from.y)
+                }
+                None => (0, 0),
+            };
+            let geometry = self.clamp_to_area(
+                window,
+                WindowInfo {
+                    x: attrs.x + dx,
+                    y: attrs.y + dy,
+                    w: attrs.width,
+                    h: attrs.height,
+                },
+            );
+            self.move_resize_window(
+                window,
+                geometry.x,
+                geometry.y,
+                geometry.w as u32,
+                geometry.h as u32,
+            );
+        }
+    }
+
+    fn focus_monitor(&mut self, step: isize) {
+        let count = self.monitors.len() as isize;
+        let monitor = (self.current_monitor as isize + step).rem_euclid(count) as usize;
+        if monitor == self.current_monitor {
+            return;
+        }
+        self.current_monitor = monitor;
+
+        // new windows open under the pointer, so take it along
+        let area = self.monitors[monitor].area;
+        unsafe {
+            let root = XDefaultRootWindow(self.display);
+            xlib::XWarpPointer(
+                self.display,
+                0,
+                root,
+                0,
+                0,
+                0,
+                0,
+                area.x + area.w / 2,
+                area.y + area.h / 2,
+            );
+        }
+        self.focus_fallback();
+        self.trigger_redraw = true;
+    }
 
-            self.current_desktop = index;
+    fn move_to_monitor(&mut self, window: Window, direction: Option<&str>) {
+        let step = if direction == Some("left") { -1 } else { 1 };
+        let count = self.monitors.len() as isize;
+        let from = self.monitor_of(window);
+        let to = (from as isize + step).rem_euclid(count) as usize;
+        if to == from || self.sticky.contains(&window) {
+            return;
         }
+
+        self.send_to_desktop(window, self.monitors[to].desktop);
+        self.fit_floating(to);
+        self.layout();
+        self.focus(window);
+        unsafe { xlib::XRaiseWindow(self.display, window) };
+        self.trigger_redraw = true;
     }
 
     fn draw_window_bar(&mut self) {
         unsafe {
-            let desktop = self.get_desktop(self.current_desktop);
+            let desktop: BTreeSet<Window> = self
+                .monitors
+                .iter()
+                .flat_map(|m| self.get_desktop(m.desktop))
+                .collect();
 
             let valid_windows: HashSet<Window> = desktop.iter().copied().collect();
             self.win_bar_windows.retain(|parent_win, bar_win| {
@@ -927,7 +3419,14 @@ impl GridWM {
                     continue;
                 }
 
-                if !self.is_tileable(*window) {
+                // tabbed windows always get their tab strip
+                let grouped = self.tab_group_of(*window);
+                if !self.is_tileable(*window)
+                    || (grouped.is_none()
+                        && (self.no_title_bar.contains(window) || !self.config.window.window_bars))
+                    || self.minimized.contains(window)
+                    || self.is_hidden_tab(*window)
+                {
                     continue;
                 }
 
@@ -951,6 +3450,11 @@ impl GridWM {
                         0,
                         0,
                     );
+                    xlib::XSelectInput(
+                        self.display,
+                        bar_win,
+                        xlib::ButtonPressMask | xlib::ButtonReleaseMask,
+                    );
                     xlib::XMapWindow(self.display, bar_win);
                     self.win_bar_windows.insert(*window, bar_win);
                     (bar_win, true)
@@ -969,6 +3473,11 @@ impl GridWM {
                     xlib::XRaiseWindow(self.display, bar_window);
                 }
 
+                if let Some(index) = grouped {
+                    self.draw_tab_strip(bar_window, index, attrs.width);
+                    continue;
+                }
+
                 let win_name = self.get_name(*window).unwrap_or_default();
                 let win_name_c = match CString::new(win_name) {
                     Ok(name) => name,
@@ -1000,60 +3509,115 @@ impl GridWM {
         }
     }
 
+    // one tab per window, the active one with swapped colors
+    fn draw_tab_strip(&self, bar_window: Window, index: usize, width: i32) {
+        let group = &self.tab_groups[index];
+        let height = self.config.window.window_bar_height;
+        let tab_width = width / group.windows.len() as i32;
+
+        for (i, &window) in group.windows.iter().enumerate() {
+            let (background, text) = if i == group.active {
+                (self.win_bar_gc, self.win_bar_background_gc)
+            } else {
+                (self.win_bar_background_gc, self.win_bar_gc)
+            };
+            let x = tab_width * i as i32;
+            let name = CString::new(self.get_name(window).unwrap_or_default()).unwrap_or_default();
+            unsafe {
+                xlib::XFillRectangle(
+                    self.display,
+                    bar_window,
+                    background,
+                    x,
+                    0,
+                    tab_width.max(1) as u32,
+                    height,
+                );
+                xlib::XDrawString(
+                    self.display,
+                    bar_window,
+                    text,
+                    x + 5,
+                    height as i32 * 3 / 4,
+                    name.as_ptr(),
+                    name.to_bytes().len() as i32,
+                );
+            }
+        }
+    }
+
     // TODO: maybe move it somewhere else
     fn draw_bar(&self, content: Option<String>) {
         unsafe {
             let root = XDefaultRootWindow(self.display);
 
-            let bar_str = match content {
-                Some(text) => CString::new(text),
-                None => CString::new(
-                    self.bar_str
-                        .replace("DESKTOP_HERE", &desktop_widget(self.current_desktop)),
-                ),
-            };
+            // every monitor has a bar showing its own desktop
+            for monitor in &self.monitors {
+                let bar_str = match &content {
+                    Some(text) => CString::new(text.clone()),
+                    None => CString::new(
+                        self.bar_str
+                            .replace("DESKTOP_HERE", &desktop_widget(monitor.desktop)),
+                    ),
+                };
 
-            let bar_str = match bar_str {
-                Ok(stri) => stri,
-                Err(e) => {
-                    warn!(
-                        "failed to create cstring for current desktop number: {}.",
-                        e
-                    );
-                    return;
-                }
-            };
+                let bar_str = match bar_str {
+                    Ok(stri) => stri,
+                    Err(e) => {
+                        warn!(
+                            "failed to create cstring for current desktop number: {}.",
+                            e
+                        );
+                        return;
+                    }
+                };
 
-            xlib::XClearArea(self.display, root, 0, 0, self.screen_width as u32, 50, 0);
-            xlib::XFillRectangle(
-                self.display,
-                root,
-                self.bar_background_gc,
-                0,
-                0,
-                self.screen_width as u32,
-                self.config.bar.height,
-            );
+                let area = monitor.area;
+                xlib::XClearArea(self.display, root, area.x, area.y, area.w as u32, 50, 0);
+                xlib::XFillRectangle(
+                    self.display,
+                    root,
+                    self.bar_background_gc,
+                    area.x,
+                    area.y,
+                    area.w as u32,
+                    self.config.bar.height,
+                );
 
-            xlib::XDrawString(
-                self.display,
-                root,
-                self.bar_gc,
-                5,
-                15,
-                bar_str.as_ptr(),
-                bar_str.to_bytes().len() as i32,
-            );
+                xlib::XDrawString(
+                    self.display,
+                    root,
+                    self.bar_gc,
+                    area.x + 5,
+                    area.y + 15,
+                    bar_str.as_ptr(),
+                    bar_str.to_bytes().len() as i32,
+                );
+            }
         }
     }
 
     fn layout(&mut self) {
-        let desktop = self.get_desktop(self.current_desktop);
-        let tileable: Vec<Window> = desktop
+        // floating windows may have changed too
+        self.update_borders();
+
+        for monitor in 0..self.monitors.len() {
+            self.layout_monitor(monitor);
+        }
+    }
+
+    fn layout_monitor(&mut self, monitor: usize) {
+        let area = self.monitors[monitor].area;
+        let desktop = self.get_desktop(self.monitors[monitor].desktop);
+        let mut tileable: Vec<Window> = desktop
             .iter()
             .copied()
             .filter(|&w| {
-                if !self.is_tileable(w) || self.floating_windows.contains(&w) {
+                if !self.is_tileable(w)
+                    || self.floating_windows.contains(&w)
+                    || self.minimized.contains(&w)
+                    || self.is_hidden_tab(w)
+                {
                     return false;
                 }
                 // check if still exists
@@ -1066,18 +3630,26 @@ impl GridWM {
             return;
         }
 
-        let positions = self.tile(
-            tileable.len(),
-            self.screen_width as i32,
-            self.screen_height as i32,
-        );
-        for (id, window) in tileable.iter().zip(positions) {
+        // windows that swallowed a terminal take its place
+        tileable.sort_by_key(|w| self.swallowed.get(w).copied().unwrap_or(*w));
+
+        let positions = self.tile(tileable.len(), area.w, area.h);
+        for (id, mut window) in tileable.iter().zip(positions) {
+            window.x += area.x;
+            window.y += area.y;
+            // tab strips need room even without title bars
+            if !self.config.window.window_bars && self.tab_group_of(*id).is_some() {
+                let strip = self.config.window.window_bar_height as i32;
+                window.y += strip;
+                window.h -= strip;
+            }
             self.resize_window(*id, window.w as u32, window.h as u32);
             self.move_window(*id, window.x, window.y);
         }
     }
 
     fn tile(&self, n: usize, screen_w: i32, screen_h: i32) -> Vec<WindowInfo> {
+        let border = self.config.window.border_width as i32;
         let cols = (n as f32).sqrt().ceil() as i32;
         let rows = (n as i32 + cols - 1) / cols;
         let w = screen_w / cols;
@@ -1095,7 +3667,7 @@ impl GridWM {
         (0..n)
             .map(|i| {
                 let i = i as i32;
-                if self.config.bar.enable && !self.config.window.window_bars {
+                let info = if self.config.bar.enable && !self.config.window.window_bars {
                     WindowInfo {
                         x: (i % cols) * w,
                         y: ((i / cols) * h) + self.config.bar.height as i32,
@@ -1127,6 +3699,12 @@ impl GridWM {
                         w,
                         h,
                     }
+                };
+                // borders are drawn around the window size
+                WindowInfo {
+                    w: (info.w - border * 2).max(1),
+                    h: (info.h - border * 2).max(1),
+                    ..info
                 }
             })
             .collect()
@@ -1226,24 +3804,13 @@ impl GridWM {
         };
     }
 
-    fn scale_down(&mut self, event: xlib::XButtonEvent) {
-        if event.subwindow == 0 {
-            return;
-        }
-
-        let win = self.get_toplevel(event.subwindow);
-
+    fn scale_down(&mut self, win: Window) {
         self.floating_windows.insert(win);
 
         self.layout();
 
         unsafe {
-            xlib::XSetInputFocus(
-                self.display,
-                win,
-                xlib::RevertToPointerRoot,
-                xlib::CurrentTime,
-            );
+            self.focus(win);
             xlib::XRaiseWindow(self.display, win);
         }
 
@@ -1259,33 +3826,23 @@ impl GridWM {
         );
     }
 
-    fn scale_up(&mut self, event: xlib::XButtonEvent) {
-        if event.subwindow == 0 {
-            return;
-        }
-
-        let win = self.get_toplevel(event.subwindow);
-
+    fn scale_up(&mut self, win: Window) {
         self.floating_windows.insert(win);
 
         self.layout();
 
         unsafe {
-            xlib::XSetInputFocus(
-                self.display,
-                win,
-                xlib::RevertToPointerRoot,
-                xlib::CurrentTime,
-            );
+            self.focus(win);
             xlib::XRaiseWindow(self.display, win);
         }
 
         let attr = self.get_window_attributes(win);
+        let screen = self.monitors[self.monitor_of(win)].area;
         // don't let the user make it too large
         let new_width = (attr.width as u32 + self.config.window.scale_steps)
-            .min((self.screen_width as f32 * 1.5) as u32);
+            .min((screen.w as f32 * 1.5) as u32);
         let new_height = (attr.height as u32 + self.config.window.scale_steps)
-            .min((self.screen_height as f32 * 1.5) as u32);
+            .min((screen.h as f32 * 1.5) as u32);
 
         self.resize_window(win, new_width, new_height);
         self.move_window(
@@ -1295,38 +3852,36 @@ impl GridWM {
         );
     }
 
-    fn handle_drag_start(&mut self, event: xlib::XButtonEvent) {
-        if event.subwindow == 0 {
-            // allow events
-            unsafe {
-                xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime);
-            }
-            return;
-        }
-
-        let win = if let Some((&parent_win, _)) = self
-            .win_bar_windows
-            .iter()
-            .find(|(_, bar_win)| **bar_win == event.subwindow)
-        {
-            parent_win
-        } else {
-            self.get_toplevel(event.subwindow)
-        };
-
+    fn handle_drag_start(&mut self, win: Window, event: xlib::XButtonEvent) {
         let attr = self.get_window_attributes(win);
 
         let mut new_x = attr.x;
         let mut new_y = attr.y;
 
         if self.floating_windows.insert(win) {
-            let new_width = (self.screen_width as f32 * 0.5) as u32;
-            let new_height = (self.screen_height as f32 * 0.5) as u32;
+            // size it was floating with before or half the screen
+            let screen = self.monitors[self.monitor_of(win)].area;
+            let (new_width, new_height) = match self.float_geometry.get(&win) {
+                Some(geometry) => (geometry.w as u32, geometry.h as u32),
+                None => (
+                    (screen.w as f32 * 0.5) as u32,
+                    (screen.h as f32 * 0.5) as u32,
+                ),
+            };
 
-            new_x = event.x_root - (new_width / 2) as i32;
-            new_y = event.y_root - (new_height / 2) as i32;
+            let geometry = self.clamp_to_area(
+                win,
+                WindowInfo {
+                    x: event.x_root - (new_width / 2) as i32,
+                    y: event.y_root - (new_height / 2) as i32,
+                    w: new_width as i32,
+                    h: new_height as i32,
+                },
+            );
+            new_x = geometry.x;
+            new_y = geometry.y;
 
-            self.move_resize_window(win, new_x, new_y, new_width, new_height);
+            self.move_resize_window(win, new_x, new_y, geometry.w as u32, geometry.h as u32);
         }
 
         self.layout();
@@ -1341,12 +3896,7 @@ impl GridWM {
 
         unsafe {
             // focus toplevel window
-            xlib::XSetInputFocus(
-                self.display,
-                win,
-                xlib::RevertToPointerRoot,
-                xlib::CurrentTime,
-            );
+            self.focus(win);
             xlib::XRaiseWindow(self.display, win);
 
             // also raise the window top bar
@@ -1364,7 +3914,7 @@ impl GridWM {
                 self.display,
                 root,
                 0, // owner_events false — handle events in WM
-                (xlib::Button1MotionMask | xlib::ButtonReleaseMask) as u32,
+                (xlib::ButtonMotionMask | xlib::ButtonReleaseMask) as u32,
                 xlib::GrabModeAsync,
                 xlib::GrabModeAsync,
                 0,
@@ -1384,25 +3934,331 @@ impl GridWM {
             let delta_x = event.x_root - state.start_mouse_x;
             let delta_y = event.y_root - state.start_mouse_y;
 
-            let new_x = state.start_win_x + delta_x;
-            let new_y = state.start_win_y + delta_y;
+            let (new_x, new_y) = self.snap_position(
+                state.window,
+                state.start_win_x + delta_x,
+                state.start_win_y + delta_y,
+            );
 
             self.move_window(state.window, new_x, new_y);
+        } else if let Some(state) = self.resize_state {
+            let delta_x = event.x_root - state.start_mouse_x;
+            let delta_y = event.y_root - state.start_mouse_y;
+
+            let new_w = (state.start_win_w + state.dir_x * delta_x).max(state.min_w);
+            let new_h = (state.start_win_h + state.dir_y * delta_y).max(state.min_h);
+
+            // keep the opposite edge in place
+            let new_x = if state.dir_x < 0 {
+                state.start_win_x + state.start_win_w - new_w
+            } else {
+                state.start_win_x
+            };
+            let new_y = if state.dir_y < 0 {
+                state.start_win_y + state.start_win_h - new_h
+            } else {
+                state.start_win_y
+            };
+
+            self.move_resize_window(state.window, new_x, new_y, new_w as u32, new_h as u32);
+        }
+    }
+
+    fn handle_resize_start(&mut self, win: Window, event: xlib::XButtonEvent) {
+        if !self.desktops.iter().any(|d| d.contains(&win)) {
+            return;
+        }
+
+        if self.floating_windows.insert(win) {
+            self.layout();
+        }
+        let attr = self.get_window_attributes(win);
+
+        // resize from the edge or corner closest to the pointer
+        let third = |start: i32, len: i32, pos: i32| {
+            if pos < start + len / 3 {
+                -1
+            } else if pos > start + len * 2 / 3 {
+                1
+            } else {
+                0
+            }
+        };
+        let mut dir_x = third(attr.x, attr.width, event.x_root);
+        let mut dir_y = third(attr.y, attr.height, event.y_root);
+        if dir_x == 0 && dir_y == 0 {
+            dir_x = 1;
+            dir_y = 1;
+        }
+
+        let shape = match (dir_x, dir_y) {
+            (-1, -1) => CURSOR_TOP_LEFT,
+            (1, -1) => CURSOR_TOP_RIGHT,
+            (-1, 1) => CURSOR_BOTTOM_LEFT,
+            (1, 1) => CURSOR_BOTTOM_RIGHT,
+            (-1, 0) => CURSOR_LEFT,
+            (1, 0) => CURSOR_RIGHT,
+            (0, -1) => CURSOR_TOP,
+            _ => CURSOR_BOTTOM,
+        };
+
+        let (min_w, min_h) = self.get_min_size(win);
+
+        unsafe {
+            let cursor = XCreateFontCursor(self.display, shape);
+
+            self.resize_state = Some(ResizeState {
+                window: win,
+                start_win_x: attr.x,
+                start_win_y: attr.y,
+                start_win_w: attr.width,
+                start_win_h: attr.height,
+                start_mouse_x: event.x_root,
+                start_mouse_y: event.y_root,
+                dir_x,
+                dir_y,
+                min_w,
+                min_h,
+                cursor,
+            });
+
+            self.focus(win);
+            xlib::XRaiseWindow(self.display, win);
+            if let Some(&bar_win) = self.win_bar_windows.get(&win) {
+                xlib::XRaiseWindow(self.display, bar_win);
+            }
+
+            let root = XDefaultRootWindow(self.display);
+            xlib::XGrabPointer(
+                self.display,
+                root,
+                0,
+                (xlib::ButtonMotionMask | xlib::ButtonReleaseMask) as u32,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                0,
+                cursor,
+                xlib::CurrentTime,
+            );
+        }
+        self.trigger_redraw = true;
+    }
+
+    // minimum size from WM_NORMAL_HINTS
+    fn get_min_size(&self, window: Window) -> (i32, i32) {
+        const DEFAULT_MIN: i32 = 100;
+        unsafe {
+            let mut hints: xlib::XSizeHints = zeroed();
+            let mut supplied: i64 = 0;
+            if xlib::XGetWMNormalHints(self.display, window, &mut hints, &mut supplied) == 0 {
+                return (DEFAULT_MIN, DEFAULT_MIN);
+            }
+
+            if hints.flags & xlib::PMinSize != 0 {
+                (hints.min_width.max(1), hints.min_height.max(1))
+            } else if hints.flags & xlib::PBaseSize != 0 {
+                (hints.base_width.max(1), hints.base_height.max(1))
+            } else {
+                (DEFAULT_MIN, DEFAULT_MIN)
+            }
+        }
+    }
+
+    // monitor area without the bar
+    fn work_area(&self, monitor: usize) -> WindowInfo {
+        let area = self.monitors[monitor].area;
+        let bar = if self.config.bar.enable {
+            (self.config.bar.height as i32).min(area.h)
+        } else {
+            0
+        };
+        WindowInfo {
+            x: area.x,
+            y: area.y + bar,
+            w: area.w,
+            h: area.h - bar,
+        }
+    }
+
+    // bounding box of the work areas of all monitors
+    fn screen_area(&self) -> WindowInfo {
+        let areas: Vec<WindowInfo> = (0..self.monitors.len())
+            .map(|m| self.work_area(m))
+            .collect();
+        let x = areas.iter().map(|a| a.x).min().unwrap_or(0);
+        let y = areas.iter().map(|a| a.y).min().unwrap_or(0);
+        let right = areas.iter().map(|a| a.x + a.w).max().unwrap_or(0);
+        let bottom = areas.iter().map(|a| a.y + a.h).max().unwrap_or(0);
+        WindowInfo {
+            x,
+            y,
+            w: right - x,
+            h: bottom - y,
+        }
+    }
+
+    fn title_bar_height(&self, window: Window) -> i32 {
+        if (self.config.window.window_bars && !self.no_title_bar.contains(&window))
+            || self.tab_group_of(window).is_some()
+        {
+            self.config.window.window_bar_height as i32
+        } else {
+            0
+        }
+    }
+
+    // pulls a floating window to nearby screen edges and other floating windows
+    fn snap_position(&self, window: Window, x: i32, y: i32) -> (i32, i32) {
+        let distance = self.config.window.snap_distance as i32;
+        if distance == 0 {
+            return (x, y);
+        }
+
+        let attrs = self.get_window_attributes(window);
+        let top_offset = self.title_bar_height(window);
+        let monitor = self
+            .monitor_at(x + attrs.width / 2, y + attrs.height / 2)
+            .unwrap_or_else(|| self.monitor_of(window));
+        let area = self.work_area(monitor);
+
+        let mut xs = vec![area.x, area.x + area.w];
+        let mut ys = vec![area.y, area.y + area.h];
+        for other in self.get_desktop(self.desktop_of(window)) {
+            if other == window
+                || !self.floating_windows.contains(&other)
+                || self.minimized.contains(&other)
+            {
+                continue;
+            }
+            let other_attrs = self.get_window_attributes(other);
+            xs.push(other_attrs.x);
+            xs.push(other_attrs.x + other_attrs.width);
+            ys.push(other_attrs.y - self.title_bar_height(other));
+            ys.push(other_attrs.y + other_attrs.height);
         }
+
+        (
+            snap_axis(x, attrs.width, &xs, distance),
+            snap_axis(y - top_offset, attrs.height + top_offset, &ys, distance) + top_offset,
+        )
+    }
+
+    // half screen at the sides, quarters in the corners, maximized at the top
+    fn snap_to_zone(&mut self, window: Window, pointer_x: i32, pointer_y: i32) {
+        const EDGE: i32 = 2;
+        let Some(monitor) = self.monitor_at(pointer_x, pointer_y) else {
+            return;
+        };
+        let screen = self.monitors[monitor].area;
+        let area = self.work_area(monitor);
+        let corner = screen.h / 10;
+
+        let at_left = pointer_x <= screen.x + EDGE;
+        let at_right = pointer_x >= screen.x + screen.w - 1 - EDGE;
+        let at_top = pointer_y <= screen.y + EDGE;
+
+        let zone = if at_left || at_right {
+            let half_w = area.w / 2;
+            let (x, w) = if at_left {
+                (area.x, half_w)
+            } else {
+                (area.x + half_w, area.w - half_w)
+            };
+            let half_h = area.h / 2;
+            if pointer_y <= screen.y + corner {
+                WindowInfo {
+                    x,
+                    y: area.y,
+                    w,
+                    h: half_h,
+                }
+            } else if pointer_y >= screen.y + screen.h - corner {
+                WindowInfo {
+                    x,
+                    y: area.y + half_h,
+                    w,
+                    h: area.h - half_h,
+                }
+            } else {
+                WindowInfo {
+                    x,
+                    y: area.y,
+                    w,
+                    h: area.h,
+                }
+            }
+        } else if at_top {
+            area
+        } else {
+            return;
+        };
+
+        let top_offset = self.title_bar_height(window);
+        let border = 2 * self.border_width(window) as i32;
+        self.move_resize_window(
+            window,
+            zone.x,
+            zone.y + top_offset,
+            (zone.w - border).max(1) as u32,
+            (zone.h - top_offset - border).max(1) as u32,
+        );
     }
 
-    fn handle_release(&mut self, _event: xlib::XButtonEvent) {
+    fn handle_release(&mut self, event: xlib::XButtonEvent) {
+        if let Some(state) = self.drag_state
+            && self.config.window.snap_zones
+        {
+            self.snap_to_zone(state.window, event.x_root, event.y_root);
+        }
+
+        // dropped on another monitor, it now belongs to the desktop shown there
+        if let Some(state) = self.drag_state
+            && let Some(monitor) = self.monitor_at(event.x_root, event.y_root)
+            && monitor != self.monitor_of(state.window)
+        {
+            self.send_to_desktop(state.window, self.monitors[monitor].desktop);
+            self.focus(state.window);
+        }
+
         unsafe {
             xlib::XUngrabPointer(self.display, xlib::CurrentTime);
             // make sure pointer events are not blocked
             xlib::XAllowEvents(self.display, xlib::AsyncPointer, xlib::CurrentTime);
+            if let Some(state) = self.resize_state {
+                xlib::XFreeCursor(self.display, state.cursor);
+            }
         }
         self.drag_state = None;
+        self.resize_state = None;
 
         self.trigger_redraw = true;
     }
 }
 
+// pixel for a hex color, black if it can't be parsed
+fn alloc_color(display: *mut xlib::Display, hex: &str, target: &str) -> u64 {
+    unsafe {
+        let screen = XDefaultScreen(display);
+        let mut color: XColor = std::mem::zeroed();
+        let Ok(hex_str) = CString::new(hex) else {
+            error!("failed to convert {} color str to cstring", target);
+            return 0;
+        };
+        if XParseColor(
+            display,
+            XDefaultColormap(display, screen),
+            hex_str.as_ptr(),
+            &mut color,
+        ) != 1
+        {
+            error!("failed to parse {} color", target);
+            return 0;
+        }
+        XAllocColor(display, XDefaultColormap(display, screen), &mut color);
+        color.pixel
+    }
+}
+
 // returns (background_gc, content_gc)
 fn create_gc(
     display: *mut x11::xlib::_XDisplay,
@@ -1502,17 +4358,179 @@ fn create_gc(
     }
 }
 
-fn get_screen_size(display: *mut xlib::Display) -> Result<(i16, i16), GridWMError> {
+// runs a command line without waiting for it, returns its pid
+fn spawn(command: &str) -> Result<u32, GridWMError> {
+    let parts = shell_words::split(command).map_err(|e| GridWMError::Other(e.to_string()))?;
+    let Some((program, args)) = parts.split_first() else {
+        return Err(GridWMError::Other("empty command".to_string()));
+    };
+    Ok(Command::new(program).args(args).spawn()?.id())
+}
+
+// moves a span so that one of its ends lines up with the closest edge in reach
+fn snap_axis(start: i32, len: i32, edges: &[i32], distance: i32) -> i32 {
+    let mut best: Option<(i32, i32)> = None;
+    for &edge in edges {
+        for candidate in [edge, edge - len] {
+            let off = (start - candidate).abs();
+            if off <= distance && best.is_none_or(|(best_off, _)| off < best_off) {
+                best = Some((off, candidate));
+            }
+        }
+    }
+    best.map_or(start, |(_, candidate)| candidate)
+}
+
+// lets the user choose one of the items with a dmenu-like program
+fn pick(command: &str, items: &[String]) -> Option<usize> {
+    let parts = match shell_words::split(command) {
+        Ok(parts) => parts,
+        Err(e) => {
+            error!("failed to parse menu command '{}': {}", command, e);
+            return None;
+        }
+    };
+    let (program, args) = parts.split_first()?;
+
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            error!("failed to run menu '{}': {}", command, e);
+            return None;
+        }
+    };
+
+    // numbered so that windows with the same title can be told apart
+    if let Some(mut stdin) = child.stdin.take() {
+        for (i, item) in items.iter().enumerate() {
+            let _ = writeln!(stdin, "{}: {}", i + 1, item);
+        }
+    }
+
+    let output = child.wait_with_output().ok()?;
+    let selection = String::from_utf8_lossy(&output.stdout);
+    let (num, _) = selection.split_once(':')?;
+    num.trim().parse::<usize>().ok()?.checked_sub(1)
+}
+
+// parsed once so an invalid value is only reported at startup
+fn parse_update_ms(value: &str) -> Option<u64> {
+    match value {
+        "auto" => None,
+        "" => Some(0),
+        value => Some(value.parse().unwrap_or_else(|_| {
+            warn!("general.update_ms is not a valid number or set to \"auto\", using 0");
+            0
+        })),
+    }
+}
+
+// refresh rate of the active mode, 60 if unknown
+fn get_refresh_rate(display: *mut xlib::Display) -> i16 {
+    unsafe {
+        let root = XDefaultRootWindow(display);
+
+        let screen_resources = XRRGetScreenResources(display, root);
+        if screen_resources.is_null() {
+            return 60;
+        }
+        let mut active_mode_id: RRMode = 0;
+        for i in 0..(*screen_resources).ncrtc {
+            let crtc = *(*screen_resources).crtcs.offset(i as isize);
+            let crtc_info = XRRGetCrtcInfo(display, screen_resources, crtc);
+            if crtc_info.is_null() {
+                continue;
+            }
+
+            if (*crtc_info).mode != 0 {
+                active_mode_id = (*crtc_info).mode;
+            }
+            XRRFreeCrtcInfo(crtc_info);
+        }
+
+        let mut active_rate = 0;
+        for i in 0..(*screen_resources).nmode {
+            let mode_info = (*screen_resources).modes.offset(i as isize);
+            if (*mode_info).id == active_mode_id {
+                let total_pixels = (*mode_info).hTotal as u64 * (*mode_info).vTotal as u64;
+                if let Some(rate) = (*mode_info).dotClock.checked_div(total_pixels) {
+                    active_rate = rate;
+                }
+            }
+        }
+        XRRFreeScreenResources(screen_resources);
+
+        if active_rate > 0 {
+            active_rate as i16
+        } else {
+            60
+        }
+    }
+}
+
+// for every new monitor the old one it most likely is: same geometry, then same
+// position or size (changed resolution or moved), then whatever is left in order
+fn match_monitors(old: &[WindowInfo], new: &[WindowInfo]) -> Vec<Option<usize>> {
+    let mut matched: Vec<Option<usize>> = vec![None; new.len()];
+    let passes: [fn(&WindowInfo, &WindowInfo) -> bool; 4] = [
+        |a, b| a == b,
+        |a, b| a.x == b.x && a.y == b.y,
+        |a, b| a.w == b.w && a.h == b.h,
+        |_, _| true,
+    ];
+    for same in passes {
+        for (i, area) in new.iter().enumerate() {
+            if matched[i].is_some() {
+                continue;
+            }
+            matched[i] =
+                (0..old.len()).find(|&o| !matched.contains(&Some(o)) && same(&old[o], area));
+        }
+    }
+    matched
+}
+
+// geometry of every monitor from left to right, the whole screen without xinerama
+fn get_monitors(display: *mut xlib::Display) -> Result<Vec<WindowInfo>, GridWMError> {
     unsafe {
+        let mut monitors: Vec<WindowInfo> = Vec::new();
         let mut num: i32 = 0;
         let screen_pointers = xinerama::XineramaQueryScreens(display, &mut num);
-        let screens = slice::from_raw_parts(screen_pointers, num as usize).to_vec();
-        let screen = screens.first();
+        if !screen_pointers.is_null() {
+            for screen in slice::from_raw_parts(screen_pointers, num as usize) {
+                let area = WindowInfo {
+                    x: screen.x_org as i32,
+                    y: screen.y_org as i32,
+                    w: screen.width as i32,
+                    h: screen.height as i32,
+                };
+                // mirrored outputs show up more than once
+                if !monitors
+                    .iter()
+                    .any(|m| m.x == area.x && m.y == area.y && m.w == area.w && m.h == area.h)
+                {
+                    monitors.push(area);
+                }
+            }
+            xlib::XFree(screen_pointers as *mut _);
+        }
 
-        if let Some(screen) = screen {
-            Ok((screen.width, screen.height))
-        } else {
-            Err(GridWMError::ScreenNotFound("0".to_string()))
+        if monitors.is_empty() {
+            let screen = XDefaultScreen(display);
+            let w = xlib::XDisplayWidth(display, screen);
+            let h = xlib::XDisplayHeight(display, screen);
+            if w <= 0 || h <= 0 {
+                return Err(GridWMError::ScreenNotFound(screen.to_string()));
+            }
+            monitors.push(WindowInfo { x: 0, y: 0, w, h });
         }
+
+        monitors.sort_by_key(|m| (m.x, m.y));
+        Ok(monitors)
     }
 }
@@ -0,0 +1,44 @@
+use std::{ffi::CStr, fs};
+
+// reads the parent pid from /proc/<pid>/stat
+pub fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name is in parentheses and may contain spaces
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+// pids of all ancestors of a process, closest first
+pub fn ancestors(pid: u32) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut current = pid;
+    while let Some(parent) = parent_pid(current) {
+        if parent <= 1 || result.contains(&parent) {
+            break;
+        }
+        result.push(parent);
+        current = parent;
+    }
+    result
+}
+
+pub fn hostname() -> Option<String> {
+    let mut buf = [0 as libc::c_char; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len()) } != 0 {
+        return None;
+    }
+    buf[buf.len() - 1] = 0;
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+// arguments a process was started with
+pub fn cmdline(pid: u32) -> Option<Vec<String>> {
+    let data = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args: Vec<String> = data
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then_some(args)
+}
@@ -0,0 +1,103 @@
+use log::warn;
+use regex::Regex;
+
+use crate::gridwm::config::Rule;
+
+// window properties rules are matched against
+#[derive(Debug, Default)]
+pub struct WindowProps {
+    pub instance: String,
+    pub class: String,
+    pub role: String,
+    pub window_type: String,
+    pub title: String,
+}
+
+// a rule with its title regex compiled
+#[derive(Debug)]
+pub struct CompiledRule {
+    rule: Rule,
+    title: Option<Regex>,
+}
+
+// compiles the rules once, rules with an invalid title regex are reported and skipped
+pub fn compile_rules(rules: &[Rule]) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let title = match rule.title.as_deref().map(Regex::new).transpose() {
+                Ok(title) => title,
+                Err(e) => {
+                    warn!("invalid title regex in rule {:?}: {}", rule, e);
+                    return None;
+                }
+            };
+            Some(CompiledRule {
+                rule: rule.clone(),
+                title,
+            })
+        })
+        .collect()
+}
+
+impl CompiledRule {
+    pub fn matches(&self, props: &WindowProps) -> bool {
+        let rule = &self.rule;
+        if let Some(class) = &rule.class
+            && *class != props.class
+        {
+            return false;
+        }
+
+        if let Some(instance) = &rule.instance
+            && *instance != props.instance
+        {
+            return false;
+        }
+
+        if let Some(role) = &rule.role
+            && *role != props.role
+        {
+            return false;
+        }
+
+        // "dialog" and "_NET_WM_WINDOW_TYPE_DIALOG" both work
+        if let Some(window_type) = &rule.window_type {
+            let short = props
+                .window_type
+                .strip_prefix("_NET_WM_WINDOW_TYPE_")
+                .unwrap_or(&props.window_type);
+            if !window_type.eq_ignore_ascii_case(short)
+                && !window_type.eq_ignore_ascii_case(&props.window_type)
+            {
+                return false;
+            }
+        }
+
+        if let Some(title) = &self.title
+            && !title.is_match(&props.title)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+// combines the effects of all matching rules, later rules win
+pub fn apply_rules(rules: &[CompiledRule], props: &WindowProps) -> Rule {
+    let mut effects = Rule::default();
+    for rule in rules.iter().filter(|r| r.matches(props)).map(|r| &r.rule) {
+        effects.desktop = rule.desktop.or(effects.desktop);
+        effects.floating = rule.floating.or(effects.floating);
+        effects.geometry = rule.geometry.or(effects.geometry);
+        effects.fullscreen = rule.fullscreen.or(effects.fullscreen);
+        effects.no_title_bar = rule.no_title_bar.or(effects.no_title_bar);
+        effects.focus = rule.focus.or(effects.focus);
+        effects.sticky = rule.sticky.or(effects.sticky);
+        effects.terminal = rule.terminal.or(effects.terminal);
+        effects.no_swallow = rule.no_swallow.or(effects.no_swallow);
+        effects.placement = rule.placement.clone().or(effects.placement);
+    }
+    effects
+}
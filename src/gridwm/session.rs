@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{error::GridWMError, rules::WindowProps};
+
+// saved arrangement of windows on the desktops
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub windows: Vec<SessionWindow>,
+}
+
+// a window as it was when the session was saved
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionWindow {
+    pub class: String,
+    pub instance: String,
+    pub title: String,
+    pub command: String,
+    // numbered from 1 like in the config
+    pub desktop: usize,
+    pub floating: bool,
+    pub geometry: Option<[i32; 4]>,
+}
+
+impl SessionWindow {
+    // titles change too often to identify windows by them
+    pub fn matches(&self, props: &WindowProps) -> bool {
+        self.class == props.class && self.instance == props.instance
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut p| {
+        p.push("gridwm/session.toml");
+        p
+    })
+}
+
+pub fn save(path: &Path, session: &Session) -> Result<(), GridWMError> {
+    let data = toml::to_string(session).map_err(|e| GridWMError::Other(e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, data)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Session, GridWMError> {
+    let data = fs::read_to_string(path)?;
+    Ok(toml::from_str(&data)?)
+}
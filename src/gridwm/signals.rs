@@ -1,25 +1,59 @@
 use std::ffi::CString;
 
 use x11::xlib::{
-    ClientMessage, CurrentTime, Display, NoEventMask, XEvent, XInternAtom, XSendEvent,
+    self, ClientMessage, CurrentTime, Display, NoEventMask, XEvent, XInternAtom, XSendEvent,
 };
 
 use crate::gridwm::Window;
 
-pub fn send_wm_delete_window(display: *mut Display, window: Window) {
+// sends a WM_PROTOCOLS client message, extra is the third data word
+pub fn send_protocol(display: *mut Display, window: Window, protocol: &str, extra: i64) {
     unsafe {
         let mut event: XEvent = std::mem::zeroed();
         let xclient = &mut event.client_message;
         xclient.type_ = ClientMessage;
         xclient.window = window;
         let wm_protocols = CString::new("WM_PROTOCOLS").unwrap();
-        xclient.message_type = XInternAtom(display, wm_protocols.as_ptr(), 1);
+        xclient.message_type = XInternAtom(display, wm_protocols.as_ptr(), 0);
         xclient.format = 32;
-        let wm_delete_window = CString::new("WM_DELETE_WINDOW").unwrap();
-        let wm_delete_atom = XInternAtom(display, wm_delete_window.as_ptr(), 0);
-        xclient.data.set_long(0, wm_delete_atom as i64);
+        let protocol = CString::new(protocol).unwrap();
+        let protocol_atom = XInternAtom(display, protocol.as_ptr(), 0);
+        xclient.data.set_long(0, protocol_atom as i64);
         xclient.data.set_long(1, CurrentTime as i64);
+        xclient.data.set_long(2, extra);
 
         XSendEvent(display, window, 0, NoEventMask, &mut event);
     }
 }
+
+pub fn send_wm_delete_window(display: *mut Display, window: Window) {
+    send_protocol(display, window, "WM_DELETE_WINDOW", 0);
+}
+
+pub fn send_wm_take_focus(display: *mut Display, window: Window) {
+    send_protocol(display, window, "WM_TAKE_FOCUS", 0);
+}
+
+pub fn send_net_wm_ping(display: *mut Display, window: Window) {
+    send_protocol(display, window, "_NET_WM_PING", window as i64);
+}
+
+// checks if a window lists a protocol in WM_PROTOCOLS
+pub fn has_protocol(display: *mut Display, window: Window, protocol: &str) -> bool {
+    unsafe {
+        let protocol = CString::new(protocol).unwrap();
+        let protocol_atom = XInternAtom(display, protocol.as_ptr(), 0);
+
+        let mut protocols: *mut xlib::Atom = std::ptr::null_mut();
+        let mut count: i32 = 0;
+        if xlib::XGetWMProtocols(display, window, &mut protocols, &mut count) == 0
+            || protocols.is_null()
+        {
+            return false;
+        }
+
+        let found = std::slice::from_raw_parts(protocols, count as usize).contains(&protocol_atom);
+        xlib::XFree(protocols as *mut _);
+        found
+    }
+}
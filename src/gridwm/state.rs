@@ -0,0 +1,71 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use x11::xlib::Window;
+
+use super::error::GridWMError;
+
+// what a restarted gridwm needs to pick up where the old one stopped
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartState {
+    pub monitor_desktops: Vec<usize>,
+    pub current_monitor: usize,
+    pub focused: Option<Window>,
+    pub desktops: Vec<Vec<Window>>,
+    pub floating: Vec<Window>,
+    pub float_geometry: Vec<SavedGeometry>,
+    pub minimized: Vec<Window>,
+    pub scratchpad: Vec<Window>,
+    pub named_scratchpads: Vec<(String, Window)>,
+    // (window, terminal it swallowed)
+    pub swallowed: Vec<(Window, Window)>,
+    pub tab_groups: Vec<SavedTabGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedGeometry {
+    pub window: Window,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedTabGroup {
+    pub windows: Vec<Window>,
+    pub active: usize,
+}
+
+// one file per display so nested sessions don't mix
+fn state_path(display_name: &str) -> PathBuf {
+    let mut path = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+    path.push(format!(
+        "gridwm-restart-{}.toml",
+        display_name.replace([':', '/'], "_")
+    ));
+    path
+}
+
+pub fn save(display_name: &str, state: &RestartState) -> Result<(), GridWMError> {
+    let data = toml::to_string(state).map_err(|e| GridWMError::Other(e.to_string()))?;
+    fs::write(state_path(display_name), data)?;
+    Ok(())
+}
+
+// reads the state once, it is only meant for the next start
+pub fn take(display_name: &str) -> Option<RestartState> {
+    let path = state_path(display_name);
+    let data = fs::read_to_string(&path).ok()?;
+    if let Err(e) = fs::remove_file(&path) {
+        warn!("failed to remove restart state: {}", e);
+    }
+    match toml::from_str(&data) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            error!("failed to parse restart state: {}", e);
+            None
+        }
+    }
+}
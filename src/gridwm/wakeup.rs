@@ -0,0 +1,78 @@
+use std::{io, os::fd::RawFd, time::Duration};
+
+// pipe that lets other threads wake up the main loop
+pub struct Wakeup {
+    read: RawFd,
+    write: RawFd,
+}
+
+// write end of the pipe, can be moved into threads
+#[derive(Clone, Copy)]
+pub struct Waker(RawFd);
+
+impl Wakeup {
+    pub fn new() -> io::Result<Self> {
+        let mut fds = [0; 2];
+        // close on exec so a restarted gridwm doesn't inherit it
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            read: fds[0],
+            write: fds[1],
+        })
+    }
+
+    pub fn waker(&self) -> Waker {
+        Waker(self.write)
+    }
+
+    // empties the pipe so the next poll blocks again
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        while unsafe { libc::read(self.read, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+    }
+
+    // sleeps until the x connection is readable, a waker was used or the timeout passed
+    pub fn wait(&self, x_fd: RawFd, timeout: Option<Duration>) {
+        let mut fds = [
+            libc::pollfd {
+                fd: x_fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.read,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        // round up so timers don't wake up a millisecond early
+        let timeout = timeout.map_or(-1, |t| {
+            t.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+        });
+
+        // EINTR is fine, the loop checks everything again anyway
+        unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if fds[1].revents & libc::POLLIN != 0 {
+            self.drain();
+        }
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+impl Waker {
+    pub fn wake(&self) {
+        // a full pipe already wakes the loop, so errors don't matter
+        let byte = 1u8;
+        unsafe { libc::write(self.0, &byte as *const u8 as *const _, 1) };
+    }
+}
@@ -19,6 +19,14 @@ fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    // started first so problems while loading the config are logged
+    match simple_logger::init() {
+        Ok(_) => {}
+        Err(e) => {
+            println!("failed to start logger: {}", e);
+        }
+    }
+
     let display_name = std::env::var("DISPLAY")?;
 
     let mut wm = GridWM::new(&display_name)?;